@@ -0,0 +1,370 @@
+//! A multi-producer, multi-consumer queue where every message is delivered to
+//! all receivers.
+//!
+//! Channel creation provides [`Sender`] and [`Receiver`] handles. Every
+//! message sent with [`Sender::send`] is cloned out to each [`Receiver`] that
+//! was subscribed at the time of the send. [`Receiver`] implements [`Stream`],
+//! and [`Sender`] implements the `Sink` trait. Additional receivers can be
+//! created at any time with [`Sender::subscribe`]; they only observe messages
+//! sent after they were subscribed.
+//!
+//! # Lagging
+//!
+//! The channel retains at most `capacity` messages in a ring buffer, and
+//! sending never waits for slow receivers. Once the buffer is full, each send
+//! overwrites the oldest message. A receiver which hadn't read the overwritten
+//! messages yet yields a [`RecvError`] reporting how many messages it missed,
+//! and then continues with the oldest message still retained.
+//!
+//! # Disconnection
+//!
+//! When all [`Sender`] handles have been dropped, or
+//! [`Sender::close_channel`] is called, no further messages can be sent. Each
+//! receiver still yields the messages it has not read yet, after which its
+//! stream terminates.
+//!
+//! [`Sender`]: struct.Sender.html
+//! [`Receiver`]: struct.Receiver.html
+//! [`RecvError`]: struct.RecvError.html
+//! [`Sender::send`]: struct.Sender.html#method.send
+//! [`Sender::subscribe`]: struct.Sender.html#method.subscribe
+//! [`Sender::close_channel`]: struct.Sender.html#method.close_channel
+//! [`Stream`]: ../../futures_core/stream/trait.Stream.html
+
+use futures_core::stream::Stream;
+use futures_core::task::{self, Poll};
+use std::any::Any;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::marker::Unpin;
+use std::pin::PinMut;
+use std::sync::{Arc, Mutex};
+
+use crate::wakers::Wakers;
+
+/// The transmission end of a broadcast channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// The receiving end of a broadcast channel.
+///
+/// This value is created by the [`channel`](channel) function or by
+/// [`Sender::subscribe`](Sender::subscribe).
+#[derive(Debug)]
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+
+    // Position of the next message this receiver will read.
+    pos: u64,
+
+    // Key of the slot holding this receiver's task in `State::wakers`.
+    key: usize,
+}
+
+// We never project PinMut<Sender> or PinMut<Receiver> to `PinMut<T>`
+impl<T> Unpin for Sender<T> {}
+impl<T> Unpin for Receiver<T> {}
+
+/// The error type returned from [`send`](Sender::send) when there are no
+/// receivers left to deliver the message to.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// The error yielded by a [`Receiver`](Receiver) that fell so far behind the
+/// senders that some messages were overwritten before it could read them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecvError {
+    lagged: u64,
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("SendError")
+            .field(&"...")
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "send failed because there are no receivers")
+    }
+}
+
+impl<T: Any> Error for SendError<T> {
+    fn description(&self) -> &str {
+        "send failed because there are no receivers"
+    }
+}
+
+impl<T> SendError<T> {
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "receiver lagged behind by {} messages", self.lagged)
+    }
+}
+
+impl Error for RecvError {
+    fn description(&self) -> &str {
+        "receiver lagged behind"
+    }
+}
+
+impl RecvError {
+    /// Returns the number of messages the receiver missed.
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    // Max number of messages retained by the channel.
+    capacity: usize,
+
+    state: Mutex<State<T>>,
+}
+
+#[derive(Debug)]
+struct State<T> {
+    // The retained messages. `buffer[0]` is the message at position `head`.
+    buffer: VecDeque<T>,
+
+    // Position of the oldest retained message. Positions count every message
+    // ever sent on the channel.
+    head: u64,
+
+    // `false` once all senders are gone or the channel was closed.
+    is_open: bool,
+
+    // Number of senders in existence
+    num_senders: usize,
+
+    // Number of receivers in existence
+    num_receivers: usize,
+
+    // Tasks of the receivers waiting for a message.
+    wakers: Wakers,
+}
+
+impl<T> State<T> {
+    // Position the next message sent will be stored at.
+    fn tail(&self) -> u64 {
+        self.head + self.buffer.len() as u64
+    }
+}
+
+/// Creates a bounded broadcast channel for communicating between asynchronous
+/// tasks.
+///
+/// The channel retains the last `capacity` messages sent. Receivers that fall
+/// further behind than that miss messages, which is reported to them with a
+/// [`RecvError`](RecvError).
+///
+/// The [`Receiver`](Receiver) returned implements the
+/// [`Stream`](futures_core::stream::Stream) trait, while [`Sender`](Sender)
+/// implements `Sink`. More receivers can be created with
+/// [`Sender::subscribe`](Sender::subscribe).
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "broadcast channel capacity must be at least 1");
+
+    let inner = Arc::new(Inner {
+        capacity,
+        state: Mutex::new(State {
+            buffer: VecDeque::with_capacity(capacity),
+            head: 0,
+            is_open: true,
+            num_senders: 1,
+            num_receivers: 0,
+            wakers: Wakers::new(),
+        }),
+    });
+
+    let tx = Sender {
+        inner,
+    };
+    let rx = tx.subscribe();
+
+    (tx, rx)
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T> Sender<T> {
+    /// Sends a message to every receiver subscribed to the channel.
+    ///
+    /// This never waits: if the channel is full the oldest message it retains
+    /// is overwritten. On success the number of receivers the message will be
+    /// delivered to is returned.
+    ///
+    /// An error is returned with the message if there are no receivers or the
+    /// channel has been closed.
+    pub fn send(&self, msg: T) -> Result<usize, SendError<T>> {
+        let (num_receivers, wakers) = {
+            let mut state = self.inner.state.lock().unwrap();
+
+            if !state.is_open || state.num_receivers == 0 {
+                return Err(SendError(msg));
+            }
+
+            if state.buffer.len() == self.inner.capacity {
+                state.buffer.pop_front();
+                state.head += 1;
+            }
+            state.buffer.push_back(msg);
+
+            (state.num_receivers, state.wakers.take_all())
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+
+        Ok(num_receivers)
+    }
+
+    /// Creates a new [`Receiver`](Receiver) for this channel.
+    ///
+    /// The receiver will observe every message sent after this call, but none
+    /// of the messages sent before it.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.inner.state.lock().unwrap();
+        state.num_receivers += 1;
+
+        Receiver {
+            inner: self.inner.clone(),
+            pos: state.tail(),
+            key: state.wakers.insert(),
+        }
+    }
+
+    /// Returns the number of receivers currently subscribed to the channel.
+    pub fn receiver_count(&self) -> usize {
+        self.inner.state.lock().unwrap().num_receivers
+    }
+
+    /// Returns whether this channel is closed without needing a context.
+    pub fn is_closed(&self) -> bool {
+        !self.inner.state.lock().unwrap().is_open
+    }
+
+    /// Closes this channel from the sender side, preventing any new messages.
+    ///
+    /// Receivers can still read the messages retained by the channel.
+    pub fn close_channel(&self) {
+        self.inner.close();
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.inner.state.lock().unwrap().num_senders += 1;
+
+        Sender {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let last = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.num_senders -= 1;
+            state.num_senders == 0
+        };
+
+        if last {
+            self.inner.close();
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = Result<T, RecvError>;
+
+    fn poll_next(
+        mut self: PinMut<Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let mut state = this.inner.state.lock().unwrap();
+
+        // The messages this receiver was about to read have been overwritten,
+        // skip ahead to the oldest message still retained.
+        if this.pos < state.head {
+            let lagged = state.head - this.pos;
+            this.pos = state.head;
+            return Poll::Ready(Some(Err(RecvError { lagged })));
+        }
+
+        let offset = (this.pos - state.head) as usize;
+        if let Some(msg) = state.buffer.get(offset) {
+            this.pos += 1;
+            return Poll::Ready(Some(Ok(msg.clone())));
+        }
+
+        if !state.is_open {
+            return Poll::Ready(None);
+        }
+
+        state.wakers.register(this.key, cx.waker());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.inner.state.lock() {
+            state.num_receivers -= 1;
+            state.wakers.remove(self.key);
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Inner =====
+ *
+ */
+
+impl<T> Inner<T> {
+    fn close(&self) {
+        let wakers = {
+            let mut state = self.state.lock().unwrap();
+            state.is_open = false;
+            state.wakers.take_all()
+        };
+
+        // Wake up the receivers so they observe the end of the stream once
+        // they've read the remaining messages.
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
@@ -21,6 +21,14 @@ macro_rules! if_std {
 
 if_std! {
     mod lock;
+    mod wakers;
+    pub mod broadcast;
+    pub mod lossy;
+    pub mod mpmc;
     pub mod mpsc;
     pub mod oneshot;
+    pub mod priority;
+    pub mod rendezvous;
+    pub mod rpc;
+    pub mod watch;
 }
@@ -0,0 +1,357 @@
+//! A bounded multi-producer, single-consumer queue which drops messages
+//! rather than applying backpressure.
+//!
+//! Sending on a lossy channel never waits. Once the channel holds `capacity`
+//! messages, each further send drops a message: either the oldest one waiting
+//! in the channel or the one being sent, depending on the [`Overflow`] policy
+//! chosen when the channel is created. The [`Receiver`] keeps count of the
+//! messages dropped, see [`Receiver::dropped`].
+//!
+//! [`Receiver`] implements [`Stream`] and [`Sender`] implements the `Sink`
+//! trait.
+//!
+//! # Disconnection
+//!
+//! When all [`Sender`] handles have been dropped, the [`Receiver`]'s stream
+//! terminates once it has yielded the messages left in the channel. If the
+//! [`Receiver`] is dropped, all further attempts to send result in an error.
+//!
+//! [`Sender`]: struct.Sender.html
+//! [`Receiver`]: struct.Receiver.html
+//! [`Overflow`]: enum.Overflow.html
+//! [`Receiver::dropped`]: struct.Receiver.html#method.dropped
+//! [`Stream`]: ../../futures_core/stream/trait.Stream.html
+
+use futures_core::stream::Stream;
+use futures_core::task::{self, Poll, Waker};
+use std::any::Any;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::marker::Unpin;
+use std::pin::PinMut;
+use std::sync::{Arc, Mutex};
+
+pub use crate::mpsc::TryRecvError;
+
+/// What a lossy channel does with a message sent while it is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// Drop the oldest message waiting in the channel to make room for the
+    /// message being sent.
+    DropOldest,
+
+    /// Drop the message being sent, keeping the messages already waiting in
+    /// the channel.
+    DropNewest,
+}
+
+/// The transmission end of a lossy channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// The receiving end of a lossy channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+}
+
+// We never project PinMut<Sender> or PinMut<Receiver> to `PinMut<T>`
+impl<T> Unpin for Sender<T> {}
+impl<T> Unpin for Receiver<T> {}
+
+/// The error type returned from [`send`](Sender::send) when the channel has
+/// been closed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("SendError")
+            .field(&"...")
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "send failed because receiver is gone")
+    }
+}
+
+impl<T: Any> Error for SendError<T> {
+    fn description(&self) -> &str {
+        "send failed because receiver is gone"
+    }
+}
+
+impl<T> SendError<T> {
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    // Max number of messages held by the channel
+    capacity: usize,
+
+    // Which message to drop when the channel is full
+    overflow: Overflow,
+
+    state: Mutex<State<T>>,
+}
+
+#[derive(Debug)]
+struct State<T> {
+    // FIFO queue of the messages waiting to be received
+    queue: VecDeque<T>,
+
+    // `false` once the channel was closed from either side
+    is_open: bool,
+
+    // Number of senders in existence
+    num_senders: usize,
+
+    // Number of messages dropped because the channel was full
+    dropped: u64,
+
+    // Handle to the receiver's task
+    recv_task: Option<Waker>,
+}
+
+/// Creates a lossy channel for communicating between asynchronous tasks.
+///
+/// The channel holds at most `capacity` messages, messages sent beyond that
+/// are dropped according to `overflow`.
+///
+/// The [`Receiver`](Receiver) returned implements the
+/// [`Stream`](futures_core::stream::Stream) trait, while [`Sender`](Sender)
+/// implements `Sink`.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T>(capacity: usize, overflow: Overflow) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "lossy channel capacity must be at least 1");
+
+    let inner = Arc::new(Inner {
+        capacity,
+        overflow,
+        state: Mutex::new(State {
+            queue: VecDeque::with_capacity(capacity),
+            is_open: true,
+            num_senders: 1,
+            dropped: 0,
+            recv_task: None,
+        }),
+    });
+
+    let tx = Sender {
+        inner: inner.clone(),
+    };
+    let rx = Receiver {
+        inner,
+    };
+
+    (tx, rx)
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T> Sender<T> {
+    /// Sends a message on the channel.
+    ///
+    /// This never waits: if the channel is full, either the oldest message in
+    /// the channel or `msg` itself is dropped, as configured by the channel's
+    /// [`Overflow`](Overflow) policy. Dropping a message isn't considered an
+    /// error.
+    ///
+    /// An error is returned with the message if the channel has been closed.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        let (dropped, recv_task) = {
+            let mut state = self.inner.state.lock().unwrap();
+
+            if !state.is_open {
+                return Err(SendError(msg));
+            }
+
+            let dropped = if state.queue.len() < self.inner.capacity {
+                state.queue.push_back(msg);
+                None
+            } else {
+                state.dropped += 1;
+                match self.inner.overflow {
+                    Overflow::DropOldest => {
+                        let oldest = state.queue.pop_front();
+                        state.queue.push_back(msg);
+                        oldest
+                    }
+                    Overflow::DropNewest => Some(msg),
+                }
+            };
+
+            (dropped, state.recv_task.take())
+        };
+
+        // The dropped message is only destroyed once the lock is released
+        drop(dropped);
+
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether this channel is closed without needing a context.
+    pub fn is_closed(&self) -> bool {
+        !self.inner.state.lock().unwrap().is_open
+    }
+
+    /// Closes this channel from the sender side, preventing any new messages.
+    pub fn close_channel(&self) {
+        self.inner.close();
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.inner.state.lock().unwrap().num_senders += 1;
+
+        Sender {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let recv_task = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.num_senders -= 1;
+
+            if state.num_senders > 0 {
+                return;
+            }
+
+            // The receiver needs to be woken up to observe the end of the
+            // stream.
+            state.recv_task.take()
+        };
+
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T> Receiver<T> {
+    /// Returns the number of messages dropped so far because the channel was
+    /// full.
+    pub fn dropped(&self) -> u64 {
+        self.inner.state.lock().unwrap().dropped
+    }
+
+    /// Closes the receiving half of the channel, without dropping it.
+    ///
+    /// This prevents any further messages from being sent on the channel while
+    /// still enabling the receiver to drain messages that are buffered.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    /// Tries to receive the next message without notifying a context if empty.
+    ///
+    /// It is not recommended to call this function from inside of a future,
+    /// only when you've otherwise arranged to be notified when the channel is
+    /// no longer empty.
+    pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
+        match self.next_message(None) {
+            Poll::Ready(msg) => Ok(msg),
+            Poll::Pending => Err(TryRecvError::new()),
+        }
+    }
+
+    fn next_message(&mut self, cx: Option<&mut task::Context>) -> Poll<Option<T>> {
+        let mut state = self.inner.state.lock().unwrap();
+
+        if let Some(msg) = state.queue.pop_front() {
+            return Poll::Ready(Some(msg));
+        }
+
+        if !state.is_open || state.num_senders == 0 {
+            return Poll::Ready(None);
+        }
+
+        if let Some(cx) = cx {
+            state.recv_task = Some(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(
+        mut self: PinMut<Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Option<T>> {
+        self.next_message(Some(cx))
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let queue = match self.inner.state.lock() {
+            Ok(mut state) => {
+                state.is_open = false;
+                state.queue.drain(..).collect::<VecDeque<_>>()
+            }
+            Err(_) => return,
+        };
+
+        // Drop the messages that will never be received outside of the lock
+        drop(queue);
+    }
+}
+
+/*
+ *
+ * ===== impl Inner =====
+ *
+ */
+
+impl<T> Inner<T> {
+    fn close(&self) {
+        let recv_task = {
+            let mut state = self.state.lock().unwrap();
+            state.is_open = false;
+            state.recv_task.take()
+        };
+
+        // Wake up the receiver so it observes the end of the stream once it
+        // has drained the channel.
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+    }
+}
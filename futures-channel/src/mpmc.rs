@@ -0,0 +1,663 @@
+//! A multi-producer, multi-consumer queue for sending values across
+//! asynchronous tasks.
+//!
+//! This channel behaves like the [`mpsc`](crate::mpsc) channel, except that
+//! the [`Receiver`] can be cloned. Every message sent is handed to exactly one
+//! of the receivers, which makes the channel suitable for distributing work
+//! between several consumer tasks.
+//!
+//! [`Receiver`] implements [`Stream`] and [`Sender`] implements the `Sink`
+//! trait. If the channel is at capacity, the send will be rejected and the task
+//! will be notified when additional capacity is available. In other words, the
+//! channel provides backpressure.
+//!
+//! Unbounded channels are also available using the `unbounded` constructor.
+//!
+//! # Disconnection
+//!
+//! When all [`Sender`] handles have been dropped, it is no longer possible to
+//! send values into the channel. Once the remaining messages have been
+//! received, every receiver's stream terminates.
+//!
+//! If all [`Receiver`] handles are dropped, then messages can no longer be
+//! read out of the channel. In this case, all further attempts to send will
+//! result in an error.
+//!
+//! # Clean Shutdown
+//!
+//! Calling `close` on any of the receivers prevents further messages from being
+//! sent into the channel, for all receivers. The receivers can then consume
+//! the channel to completion before being dropped.
+//!
+//! [`Sender`]: struct.Sender.html
+//! [`Receiver`]: struct.Receiver.html
+//! [`Stream`]: ../../futures_core/stream/trait.Stream.html
+
+// The channel state is kept behind a single lock, but otherwise follows the
+// same protocol as the `mpsc` channel.
+//
+// The channel is created with a `buffer` size of `n`. The channel capacity is
+// `n + num-senders`: each sender gets one "guaranteed" slot to hold a message.
+// A sender which pushes a message while the channel holds more than `buffer`
+// messages parks itself by pushing its task handle onto the parked task queue,
+// and it can't send any further message until it has been unparked.
+//
+// Each message received pops exactly one handle off the parked task queue.
+// Since a handle is only ever pushed together with a message, this guarantees
+// that a sender will be unparked when the message that caused it to become
+// parked is read out of the channel.
+//
+// Every message sent wakes up (at most) one of the receivers waiting for a
+// message. A receiver which is dropped while there are messages left passes
+// the wakeup on to another waiting receiver, so no message is left behind
+// unnoticed.
+
+use futures_core::stream::Stream;
+use futures_core::task::{self, Poll, Waker};
+use std::collections::VecDeque;
+use std::marker::Unpin;
+use std::pin::PinMut;
+use std::sync::{Arc, Mutex};
+use std::usize;
+use std::vec::Vec;
+
+use crate::mpsc::SenderTask;
+use crate::wakers::Wakers;
+
+pub use crate::mpsc::{SendError, TryRecvError, TrySendError};
+
+/// The transmission end of a bounded mpmc channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Sender<T> {
+    // Channel state shared between the senders and receivers.
+    inner: Arc<Inner<T>>,
+
+    // Handle to the task that is blocked on this sender. This handle is sent
+    // to the receivers in order to be notified when the sender becomes
+    // unblocked.
+    sender_task: Arc<Mutex<SenderTask>>,
+
+    // True if the sender might be blocked. This is an optimization to avoid
+    // having to lock the mutex most of the time.
+    maybe_parked: bool,
+}
+
+// We never project PinMut<Sender> to `PinMut<T>`
+impl<T> Unpin for Sender<T> {}
+
+/// The transmission end of an unbounded mpmc channel.
+///
+/// This value is created by the [`unbounded`](unbounded) function.
+#[derive(Debug)]
+pub struct UnboundedSender<T>(Sender<T>);
+
+/// The receiving end of a bounded mpmc channel.
+///
+/// This value is created by the [`channel`](channel) function. Cloning it
+/// creates another consumer of the same channel.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+
+    // Key of the slot holding this receiver's task in `State::recv_tasks`.
+    key: usize,
+}
+
+// The receiver does not ever take a PinMut to the inner T
+impl<T> Unpin for Receiver<T> {}
+
+/// The receiving end of an unbounded mpmc channel.
+///
+/// This value is created by the [`unbounded`](unbounded) function. Cloning it
+/// creates another consumer of the same channel.
+#[derive(Debug)]
+pub struct UnboundedReceiver<T>(Receiver<T>);
+
+trait AssertKinds: Send + Sync + Clone {}
+impl AssertKinds for UnboundedSender<u32> {}
+impl AssertKinds for Receiver<u32> {}
+impl AssertKinds for UnboundedReceiver<u32> {}
+
+#[derive(Debug)]
+struct Inner<T> {
+    // Max buffer size of the channel. If `None` then the channel is unbounded.
+    buffer: Option<usize>,
+
+    state: Mutex<State<T>>,
+}
+
+#[derive(Debug)]
+struct State<T> {
+    // `false` once a receiver closed the channel or all receivers are gone
+    is_open: bool,
+
+    // FIFO queue of the messages waiting to be received
+    message_queue: VecDeque<T>,
+
+    // FIFO queue of parked task handles, popped once per message received
+    parked_queue: VecDeque<Arc<Mutex<SenderTask>>>,
+
+    // Number of senders in existence
+    num_senders: usize,
+
+    // Number of receivers in existence
+    num_receivers: usize,
+
+    // Handles to the receivers' tasks
+    recv_tasks: Wakers,
+
+    // Keys of the receivers waiting for a message, in the order they started
+    // waiting.
+    waiting: VecDeque<usize>,
+}
+
+impl<T> State<T> {
+    // Whether the receivers will never see another message being sent.
+    fn is_terminated(&self) -> bool {
+        !self.is_open || self.num_senders == 0
+    }
+
+    // Take the task of the receiver which has been waiting the longest.
+    fn next_receiver(&mut self) -> Option<Waker> {
+        while let Some(key) = self.waiting.pop_front() {
+            if let Some(task) = self.recv_tasks.take(key) {
+                return Some(task);
+            }
+        }
+        None
+    }
+
+    // Take the tasks of every waiting receiver.
+    fn all_receivers(&mut self) -> Vec<Waker> {
+        self.waiting.clear();
+        self.recv_tasks.take_all()
+    }
+}
+
+/// Creates a bounded mpmc channel for communicating between asynchronous tasks.
+///
+/// Being bounded, this channel provides backpressure to ensure that the senders
+/// outpace the receivers by only a limited amount. The channel's capacity is
+/// equal to `buffer + num-senders`. In other words, each sender gets a
+/// guaranteed slot in the channel capacity, and on top of that there are
+/// `buffer` "first come, first serve" slots available to all senders.
+///
+/// The [`Receiver`](Receiver) returned implements the
+/// [`Stream`](futures_core::stream::Stream) trait and can be cloned, while
+/// [`Sender`](Sender) implements `Sink`.
+pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    // Check that the requested buffer size does not exceed the maximum buffer
+    // size permitted by the system.
+    assert!(buffer < usize::MAX >> 1, "requested buffer size too large");
+    channel2(Some(buffer))
+}
+
+/// Creates an unbounded mpmc channel for communicating between asynchronous
+/// tasks.
+///
+/// A `send` on this channel will always succeed as long as the channel has not
+/// been closed. If the receivers fall behind, messages will be arbitrarily
+/// buffered.
+///
+/// **Note** that the amount of available system memory is an implicit bound to
+/// the channel. Using an `unbounded` channel has the ability of causing the
+/// process to run out of memory. In this case, the process will be aborted.
+pub fn unbounded<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
+    let (tx, rx) = channel2(None);
+    (UnboundedSender(tx), UnboundedReceiver(rx))
+}
+
+fn channel2<T>(buffer: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    let mut recv_tasks = Wakers::new();
+    let key = recv_tasks.insert();
+
+    let inner = Arc::new(Inner {
+        buffer,
+        state: Mutex::new(State {
+            is_open: true,
+            message_queue: VecDeque::new(),
+            parked_queue: VecDeque::new(),
+            num_senders: 1,
+            num_receivers: 1,
+            recv_tasks,
+            waiting: VecDeque::new(),
+        }),
+    });
+
+    let tx = Sender {
+        inner: inner.clone(),
+        sender_task: Arc::new(Mutex::new(SenderTask::new())),
+        maybe_parked: false,
+    };
+
+    let rx = Receiver {
+        inner,
+        key,
+    };
+
+    (tx, rx)
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T> Sender<T> {
+    /// Attempts to send a message on this `Sender`, returning the message
+    /// if there was an error.
+    pub fn try_send(&mut self, msg: T) -> Result<(), TrySendError<T>> {
+        // If the sender is currently blocked, reject the message
+        if !self.poll_unparked(None).is_ready() {
+            return Err(TrySendError::new(SendError::full(), msg));
+        }
+
+        // The channel has capacity to accept the message, so send it
+        self.do_send(None, msg)
+    }
+
+    /// Send a message on the channel.
+    ///
+    /// This function should only be called after
+    /// [`poll_ready`](Sender::poll_ready) has reported that the channel is
+    /// ready to receive a message.
+    pub fn start_send(&mut self, msg: T) -> Result<(), SendError> {
+        self.try_send(msg)
+            .map_err(|e| e.into_send_error())
+    }
+
+    // Do the send without failing
+    fn do_send(&mut self, cx: Option<&mut task::Context>, msg: T)
+        -> Result<(), TrySendError<T>>
+    {
+        let (park_self, recv_task) = {
+            let mut state = self.inner.state.lock().unwrap();
+
+            if !state.is_open {
+                return Err(TrySendError::new(SendError::disconnected(), msg));
+            }
+
+            state.message_queue.push_back(msg);
+
+            // Park if the number of pending messages has exceeded the
+            // configured buffer size. The handle is queued while the lock is
+            // still held, so it is guaranteed to be popped by the receiver of
+            // one of the messages in excess of the buffer.
+            let park_self = match self.inner.buffer {
+                Some(buffer) => state.message_queue.len() > buffer,
+                None => false,
+            };
+            if park_self {
+                {
+                    let mut sender = self.sender_task.lock().unwrap();
+                    sender.task = cx.map(|cx| cx.waker().clone());
+                    sender.is_parked = true;
+                }
+                state.parked_queue.push_back(self.sender_task.clone());
+            }
+
+            (park_self, state.next_receiver())
+        };
+
+        if park_self {
+            self.maybe_parked = true;
+        }
+
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+
+        Ok(())
+    }
+
+    /// Polls the channel to determine if there is guaranteed capacity to send
+    /// at least one item without waiting.
+    ///
+    /// # Return value
+    ///
+    /// This method returns:
+    ///
+    /// - `Poll::Ready(Ok(_))` if there is sufficient capacity;
+    /// - `Poll::Pending` if the channel may not have capacity, in which case
+    ///   the current task is queued to be notified once capacity is available;
+    /// - `Poll::Ready(Err(SendError))` if the receivers have been dropped.
+    pub fn poll_ready(
+        &mut self,
+        cx: &mut task::Context
+    ) -> Poll<Result<(), SendError>> {
+        if self.is_closed() {
+            return Poll::Ready(Err(SendError::disconnected()));
+        }
+
+        self.poll_unparked(Some(cx)).map(Ok)
+    }
+
+    /// Returns whether this channel is closed without needing a context.
+    pub fn is_closed(&self) -> bool {
+        !self.inner.state.lock().unwrap().is_open
+    }
+
+    /// Closes this channel from the sender side, preventing any new messages.
+    pub fn close_channel(&mut self) {
+        self.inner.close();
+    }
+
+    fn poll_unparked(&mut self, cx: Option<&mut task::Context>) -> Poll<()> {
+        // First check the `maybe_parked` variable. This avoids acquiring the
+        // lock in most cases
+        if self.maybe_parked {
+            // Get a lock on the task handle
+            let mut task = self.sender_task.lock().unwrap();
+
+            if !task.is_parked {
+                self.maybe_parked = false;
+                return Poll::Ready(())
+            }
+
+            // At this point, an unpark request is pending, so there will be an
+            // unpark sometime in the future. We just need to make sure that
+            // the correct task will be notified.
+            //
+            // Update the task in case the `Sender` has been moved to another
+            // task
+            task.task = cx.map(|cx| cx.waker().clone());
+
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+impl<T> UnboundedSender<T> {
+    /// Check if the channel is ready to receive a message.
+    pub fn poll_ready(
+        &self,
+        _: &mut task::Context,
+    ) -> Poll<Result<(), SendError>> {
+        if self.is_closed() {
+            Poll::Ready(Err(SendError::disconnected()))
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Returns whether this channel is closed without needing a context.
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+
+    /// Closes this channel from the sender side, preventing any new messages.
+    pub fn close_channel(&self) {
+        self.0.inner.close();
+    }
+
+    /// Send a message on the channel.
+    ///
+    /// This method should only be called after `poll_ready` has been used to
+    /// verify that the channel is ready to receive a message.
+    pub fn start_send(&mut self, msg: T) -> Result<(), SendError> {
+        self.unbounded_send(msg)
+            .map_err(|e| e.into_send_error())
+    }
+
+    /// Sends a message along this channel.
+    ///
+    /// This is an unbounded sender, so this function differs from `Sink::send`
+    /// by ensuring the return type reflects that the channel is always ready to
+    /// receive messages.
+    pub fn unbounded_send(&self, msg: T) -> Result<(), TrySendError<T>> {
+        let recv_task = {
+            let mut state = self.0.inner.state.lock().unwrap();
+
+            if !state.is_open {
+                return Err(TrySendError::new(SendError::disconnected(), msg));
+            }
+
+            state.message_queue.push_back(msg);
+            state.next_receiver()
+        };
+
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.inner.state.lock().unwrap().num_senders += 1;
+
+        Sender {
+            inner: self.inner.clone(),
+            sender_task: Arc::new(Mutex::new(SenderTask::new())),
+            maybe_parked: false,
+        }
+    }
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> UnboundedSender<T> {
+        UnboundedSender(self.0.clone())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let recv_tasks = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.num_senders -= 1;
+
+            if state.num_senders > 0 {
+                return;
+            }
+
+            // The receivers need to be woken up to observe the end of the
+            // stream.
+            state.all_receivers()
+        };
+
+        for task in recv_tasks {
+            task.wake();
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T> Receiver<T> {
+    /// Closes the channel, without dropping this receiver.
+    ///
+    /// This prevents any further messages from being sent on the channel while
+    /// still enabling the receivers to drain messages that are buffered. Note
+    /// that this closes the channel for *all* receivers, not only this one.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    /// Tries to receive the next message without notifying a context if empty.
+    ///
+    /// It is not recommended to call this function from inside of a future,
+    /// only when you've otherwise arranged to be notified when the channel is
+    /// no longer empty.
+    pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
+        match self.next_message(None) {
+            Poll::Ready(msg) => Ok(msg),
+            Poll::Pending => Err(TryRecvError::new()),
+        }
+    }
+
+    fn next_message(&mut self, cx: Option<&mut task::Context>) -> Poll<Option<T>> {
+        let (msg, sender_task) = {
+            let mut state = self.inner.state.lock().unwrap();
+
+            match state.message_queue.pop_front() {
+                Some(msg) => {
+                    // This receiver may have registered on an earlier poll.
+                    // It is done waiting, so it must not take the wakeup for
+                    // the next message from a receiver which is waiting.
+                    if state.recv_tasks.take(self.key).is_some() {
+                        let key = self.key;
+                        state.waiting.retain(|k| *k != key);
+                    }
+                    (msg, state.parked_queue.pop_front())
+                }
+                None => {
+                    if state.is_terminated() {
+                        return Poll::Ready(None);
+                    }
+
+                    if let Some(cx) = cx {
+                        if state.recv_tasks.register(self.key, cx.waker()) {
+                            state.waiting.push_back(self.key);
+                        }
+                    }
+                    return Poll::Pending;
+                }
+            }
+        };
+
+        // If there was a parked sender, unpark it as a slot has become
+        // available.
+        if let Some(task) = sender_task {
+            task.lock().unwrap().notify();
+        }
+
+        Poll::Ready(Some(msg))
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Receiver<T> {
+        let mut state = self.inner.state.lock().unwrap();
+        state.num_receivers += 1;
+
+        Receiver {
+            inner: self.inner.clone(),
+            key: state.recv_tasks.insert(),
+        }
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(
+        mut self: PinMut<Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Option<T>> {
+        self.next_message(Some(cx))
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let (messages, sender_tasks, recv_task) = {
+            let mut state = match self.inner.state.lock() {
+                Ok(state) => state,
+                Err(_) => return,
+            };
+            let key = self.key;
+
+            state.recv_tasks.remove(key);
+            state.waiting.retain(|k| *k != key);
+            state.num_receivers -= 1;
+
+            if state.num_receivers == 0 {
+                // This was the last receiver, close the channel and unpark
+                // all the senders. The messages are dropped once the lock is
+                // released.
+                state.is_open = false;
+                let messages = state.message_queue.drain(..).collect::<Vec<_>>();
+                let sender_tasks = state.parked_queue.drain(..).collect::<Vec<_>>();
+                (messages, sender_tasks, None)
+            } else if !state.message_queue.is_empty() {
+                // This receiver may have been woken up for a message it will
+                // now never receive. Pass the wakeup on to another receiver.
+                (Vec::new(), Vec::new(), state.next_receiver())
+            } else {
+                return;
+            }
+        };
+
+        drop(messages);
+        for task in sender_tasks {
+            task.lock().unwrap().notify();
+        }
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+    }
+}
+
+impl<T> UnboundedReceiver<T> {
+    /// Closes the channel, without dropping this receiver.
+    ///
+    /// This prevents any further messages from being sent on the channel while
+    /// still enabling the receivers to drain messages that are buffered. Note
+    /// that this closes the channel for *all* receivers, not only this one.
+    pub fn close(&mut self) {
+        self.0.close();
+    }
+
+    /// Tries to receive the next message without notifying a context if empty.
+    ///
+    /// It is not recommended to call this function from inside of a future,
+    /// only when you've otherwise arranged to be notified when the channel is
+    /// no longer empty.
+    pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
+        self.0.try_next()
+    }
+}
+
+impl<T> Clone for UnboundedReceiver<T> {
+    fn clone(&self) -> UnboundedReceiver<T> {
+        UnboundedReceiver(self.0.clone())
+    }
+}
+
+impl<T> Stream for UnboundedReceiver<T> {
+    type Item = T;
+
+    fn poll_next(
+        mut self: PinMut<Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Option<T>> {
+        PinMut::new(&mut self.0).poll_next(cx)
+    }
+}
+
+/*
+ *
+ * ===== impl Inner =====
+ *
+ */
+
+impl<T> Inner<T> {
+    fn close(&self) {
+        let (recv_tasks, sender_tasks) = {
+            let mut state = self.state.lock().unwrap();
+            state.is_open = false;
+            let sender_tasks = state.parked_queue.drain(..).collect::<Vec<_>>();
+            (state.all_receivers(), sender_tasks)
+        };
+
+        // Wake up any tasks waiting as they'll see that we've closed the
+        // channel and will continue on their merry way.
+        for task in sender_tasks {
+            task.lock().unwrap().notify();
+        }
+        for task in recv_tasks {
+            task.wake();
+        }
+    }
+}
@@ -78,6 +78,7 @@
 // happens-before semantics required for the acquire / release semantics used
 // by the queue structure.
 
+use futures_core::future::Future;
 use futures_core::stream::Stream;
 use futures_core::task::{self, Waker, Poll};
 use std::any::Any;
@@ -85,11 +86,12 @@ use std::error::Error;
 use std::fmt;
 use std::marker::Unpin;
 use std::pin::PinMut;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::SeqCst;
 use std::thread;
 use std::usize;
+use std::vec::Vec;
 
 use crate::mpsc::queue::{Queue, PopResult};
 
@@ -122,8 +124,30 @@ impl<T> Unpin for Sender<T> {}
 #[derive(Debug)]
 pub struct UnboundedSender<T>(Sender<T>);
 
+/// A weak handle to the transmission end of a bounded mpsc channel.
+///
+/// Unlike a [`Sender`](Sender), a `WeakSender` doesn't keep the channel open:
+/// once all senders are gone the receiver's stream terminates, even if weak
+/// senders remain. It can be upgraded back to a `Sender` for as long as the
+/// channel is open.
+///
+/// This value is created by the [`downgrade`](Sender::downgrade) method.
+#[derive(Debug)]
+pub struct WeakSender<T> {
+    inner: Weak<Inner<T>>,
+}
+
+/// A weak handle to the transmission end of an unbounded mpsc channel.
+///
+/// This value is created by the [`downgrade`](UnboundedSender::downgrade)
+/// method.
+#[derive(Debug)]
+pub struct WeakUnboundedSender<T>(WeakSender<T>);
+
 trait AssertKinds: Send + Sync + Clone {}
 impl AssertKinds for UnboundedSender<u32> {}
+impl AssertKinds for WeakSender<u32> {}
+impl AssertKinds for WeakUnboundedSender<u32> {}
 
 /// The receiving end of a bounded mpsc channel.
 ///
@@ -142,6 +166,17 @@ pub struct UnboundedReceiver<T>(Receiver<T>);
 // `PinMut<UnboundedReceiver<T>>` is never projected to `PinMut<T>`
 impl<T> Unpin for UnboundedReceiver<T> {}
 
+/// A future which receives a batch of messages from a channel.
+///
+/// This value is created by the [`recv_many`](Receiver::recv_many) method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct RecvMany<'a, T: 'a> {
+    receiver: &'a mut Receiver<T>,
+    buf: &'a mut Vec<T>,
+    limit: usize,
+}
+
 /// The error type for [`Sender`s](Sender) used as `Sink`s.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SendError {
@@ -187,6 +222,18 @@ impl Error for SendError {
 }
 
 impl SendError {
+    pub(crate) fn full() -> SendError {
+        SendError {
+            kind: SendErrorKind::Full,
+        }
+    }
+
+    pub(crate) fn disconnected() -> SendError {
+        SendError {
+            kind: SendErrorKind::Disconnected,
+        }
+    }
+
     /// Returns true if this error is a result of the channel being full.
     pub fn is_full(&self) -> bool {
         match self.kind {
@@ -233,6 +280,13 @@ impl<T: Any> Error for TrySendError<T> {
 }
 
 impl<T> TrySendError<T> {
+    pub(crate) fn new(err: SendError, val: T) -> TrySendError<T> {
+        TrySendError {
+            err,
+            val,
+        }
+    }
+
     /// Returns true if this error is a result of the channel being full.
     pub fn is_full(&self) -> bool {
         self.err.is_full()
@@ -254,6 +308,14 @@ impl<T> TrySendError<T> {
     }
 }
 
+impl TryRecvError {
+    pub(crate) fn new() -> TryRecvError {
+        TryRecvError {
+            _inner: (),
+        }
+    }
+}
+
 impl fmt::Debug for TryRecvError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_tuple("TryRecvError")
@@ -288,6 +350,11 @@ struct Inner<T> {
     // Atomic, FIFO queue used to send parked task handles to the receiver.
     parked_queue: Queue<Arc<Mutex<SenderTask>>>,
 
+    // Number of senders parked, that is whose handle is in `parked_queue` and
+    // which haven't been unparked since. Only updated while holding the lock
+    // on the sender's task handle.
+    num_parked: AtomicUsize,
+
     // Number of senders in existence
     num_senders: AtomicUsize,
 
@@ -301,7 +368,8 @@ struct State {
     // `true` when the channel is open
     is_open: bool,
 
-    // Number of messages in the channel
+    // Number of messages in the channel, not counting the `None` pushed when
+    // the senders close the channel
     num_messages: usize,
 }
 
@@ -334,20 +402,20 @@ const MAX_BUFFER: usize = MAX_CAPACITY >> 1;
 
 // Sent to the consumer to wake up blocked producers
 #[derive(Debug)]
-struct SenderTask {
-    task: Option<Waker>,
-    is_parked: bool,
+pub(crate) struct SenderTask {
+    pub(crate) task: Option<Waker>,
+    pub(crate) is_parked: bool,
 }
 
 impl SenderTask {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         SenderTask {
             task: None,
             is_parked: false,
         }
     }
 
-    fn notify(&mut self) {
+    pub(crate) fn notify(&mut self) {
         self.is_parked = false;
 
         if let Some(task) = self.task.take() {
@@ -395,6 +463,7 @@ fn channel2<T>(buffer: Option<usize>) -> (Sender<T>, Receiver<T>) {
         state: AtomicUsize::new(INIT_STATE),
         message_queue: Queue::new(),
         parked_queue: Queue::new(),
+        num_parked: AtomicUsize::new(0),
         num_senders: AtomicUsize::new(1),
         recv_task: Mutex::new(ReceiverTask {
             unparked: false,
@@ -498,12 +567,7 @@ impl<T> Sender<T> {
             None => {
                 // The receiver has closed the channel. Only abort if actually
                 // sending a message. It is important that the stream
-                // termination (None) is always sent. This technically means
-                // that it is possible for the queue to contain the following
-                // number of messages:
-                //
-                //     num-senders + buffer + 1
-                //
+                // termination (None) is always sent.
                 if let Some(msg) = msg {
                     return Err(TrySendError {
                         err: SendError {
@@ -546,6 +610,10 @@ impl<T> Sender<T> {
 
     // Increment the number of queued messages. Returns if the sender should
     // block.
+    //
+    // When `close` is set, the channel is closed instead. The `None` message
+    // terminating the stream isn't counted, so that `num_messages` only ever
+    // reflects the messages the receiver will actually yield.
     fn inc_num_messages(&self, close: bool) -> Option<bool> {
         let mut curr = self.inner.state.load(SeqCst);
 
@@ -557,17 +625,17 @@ impl<T> Sender<T> {
                 return None;
             }
 
-            // This probably is never hit? Odds are the process will run out of
-            // memory first. It may be worth to return something else in this
-            // case?
-            assert!(state.num_messages < MAX_CAPACITY, "buffer space \
-                    exhausted; sending this messages would overflow the state");
-
-            state.num_messages += 1;
-
-            // The channel is closed by all sender handles being dropped.
             if close {
+                // The channel is closed by all sender handles being dropped.
                 state.is_open = false;
+            } else {
+                // This probably is never hit? Odds are the process will run
+                // out of memory first. It may be worth to return something
+                // else in this case?
+                assert!(state.num_messages < MAX_CAPACITY, "buffer space \
+                        exhausted; sending this messages would overflow the state");
+
+                state.num_messages += 1;
             }
 
             let next = encode_state(&state);
@@ -625,6 +693,7 @@ impl<T> Sender<T> {
             let mut sender = self.sender_task.lock().unwrap();
             sender.task = task;
             sender.is_parked = true;
+            self.inner.num_parked.fetch_add(1, SeqCst);
         }
 
         // Send handle over queue
@@ -634,7 +703,18 @@ impl<T> Sender<T> {
         // Check to make sure we weren't closed after we sent our task on the
         // queue
         let state = decode_state(self.inner.state.load(SeqCst));
-        self.maybe_parked = state.is_open;
+        if state.is_open {
+            self.maybe_parked = true;
+        } else {
+            // Unpark ourselves, the receiver may never pop the handle
+            let mut sender = self.sender_task.lock().unwrap();
+            if sender.is_parked {
+                sender.is_parked = false;
+                sender.task = None;
+                self.inner.num_parked.fetch_sub(1, SeqCst);
+            }
+            self.maybe_parked = false;
+        }
     }
 
     /// Polls the channel to determine if there is guaranteed capacity to send
@@ -668,6 +748,44 @@ impl<T> Sender<T> {
         !decode_state(self.inner.state.load(SeqCst)).is_open
     }
 
+    /// Returns the number of messages waiting in the channel.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns whether there are no messages waiting in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the capacity of the channel, that is `buffer + num-senders`.
+    ///
+    /// The channel can hold this many messages before all senders are parked.
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    /// Returns the number of senders in existence for this channel.
+    pub fn num_senders(&self) -> usize {
+        self.inner.num_senders.load(SeqCst)
+    }
+
+    /// Returns the number of senders currently parked, waiting for the
+    /// receiver to make room in the channel.
+    pub fn num_parked_senders(&self) -> usize {
+        self.inner.num_parked.load(SeqCst)
+    }
+
+    /// Creates a [`WeakSender`](WeakSender) for this channel.
+    ///
+    /// The weak sender doesn't count as a live sender, so it doesn't prevent
+    /// the receiver's stream from terminating.
+    pub fn downgrade(&self) -> WeakSender<T> {
+        WeakSender {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+
     /// Closes this channel from the sender side, preventing any new messages.
     pub fn close_channel(&mut self) {
         // There's no need to park this sender, its dropping,
@@ -718,6 +836,21 @@ impl<T> UnboundedSender<T> {
         self.0.is_closed()
     }
 
+    /// Returns the number of messages waiting in the channel.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether there are no messages waiting in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of senders in existence for this channel.
+    pub fn num_senders(&self) -> usize {
+        self.0.num_senders()
+    }
+
     /// Closes this channel from the sender side, preventing any new messages.
     pub fn close_channel(&self) {
         // There's no need to park this sender, its dropping,
@@ -744,6 +877,15 @@ impl<T> UnboundedSender<T> {
     pub fn unbounded_send(&self, msg: T) -> Result<(), TrySendError<T>> {
         self.0.do_send_nb(Some(msg))
     }
+
+    /// Creates a [`WeakUnboundedSender`](WeakUnboundedSender) for this
+    /// channel.
+    ///
+    /// The weak sender doesn't count as a live sender, so it doesn't prevent
+    /// the receiver's stream from terminating.
+    pub fn downgrade(&self) -> WeakUnboundedSender<T> {
+        WeakUnboundedSender(self.0.downgrade())
+    }
 }
 
 impl<T> Clone for UnboundedSender<T> {
@@ -774,11 +916,7 @@ impl<T> Clone for Sender<T> {
             // The ABA problem doesn't matter here. We only care that the
             // number of senders never exceeds the maximum.
             if actual == curr {
-                return Sender {
-                    inner: self.inner.clone(),
-                    sender_task: Arc::new(Mutex::new(SenderTask::new())),
-                    maybe_parked: false,
-                };
+                return Sender::from_inner(self.inner.clone());
             }
 
             curr = actual;
@@ -786,6 +924,18 @@ impl<T> Clone for Sender<T> {
     }
 }
 
+impl<T> Sender<T> {
+    // Creates a sender for a channel whose sender count already accounts for
+    // it.
+    fn from_inner(inner: Arc<Inner<T>>) -> Sender<T> {
+        Sender {
+            inner,
+            sender_task: Arc::new(Mutex::new(SenderTask::new())),
+            maybe_parked: false,
+        }
+    }
+}
+
 impl<T> Drop for Sender<T> {
     fn drop(&mut self) {
         // Ordering between variables don't matter here
@@ -800,6 +950,69 @@ impl<T> Drop for Sender<T> {
     }
 }
 
+/*
+ *
+ * ===== impl WeakSender =====
+ *
+ */
+
+impl<T> WeakSender<T> {
+    /// Attempts to upgrade this weak handle to a [`Sender`](Sender).
+    ///
+    /// Returns `None` if all senders have been dropped or the channel has been
+    /// closed.
+    pub fn upgrade(&self) -> Option<Sender<T>> {
+        let inner = self.inner.upgrade()?;
+        let mut curr = inner.num_senders.load(SeqCst);
+
+        loop {
+            // Once the last sender is gone the channel is closed for good, a
+            // sender must not be resurrected.
+            if curr == 0 || !decode_state(inner.state.load(SeqCst)).is_open {
+                return None;
+            }
+
+            if curr == inner.max_senders() {
+                panic!("cannot upgrade `WeakSender` -- too many outstanding senders");
+            }
+
+            let actual = inner.num_senders.compare_and_swap(curr, curr + 1, SeqCst);
+
+            // As in `Sender::clone`, the ABA problem doesn't matter here
+            if actual == curr {
+                return Some(Sender::from_inner(inner));
+            }
+
+            curr = actual;
+        }
+    }
+}
+
+impl<T> Clone for WeakSender<T> {
+    fn clone(&self) -> WeakSender<T> {
+        WeakSender {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> WeakUnboundedSender<T> {
+    /// Attempts to upgrade this weak handle to an
+    /// [`UnboundedSender`](UnboundedSender).
+    ///
+    /// Returns `None` if all senders have been dropped or the channel has been
+    /// closed.
+    pub fn upgrade(&self) -> Option<UnboundedSender<T>> {
+        self.0.upgrade().map(UnboundedSender)
+    }
+}
+
+impl<T> Clone for WeakUnboundedSender<T> {
+    fn clone(&self) -> WeakUnboundedSender<T> {
+        WeakUnboundedSender(self.0.clone())
+    }
+}
+
 /*
  *
  * ===== impl Receiver =====
@@ -835,7 +1048,7 @@ impl<T> Receiver<T> {
         loop {
             match unsafe { self.inner.parked_queue.pop() } {
                 PopResult::Data(task) => {
-                    task.lock().unwrap().notify();
+                    self.inner.unpark_sender(&task);
                 }
                 PopResult::Empty => break,
                 PopResult::Inconsistent => thread::yield_now(),
@@ -843,6 +1056,87 @@ impl<T> Receiver<T> {
         }
     }
 
+    /// Returns the number of messages waiting in the channel.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns whether there are no messages waiting in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the capacity of the channel, that is `buffer + num-senders`.
+    ///
+    /// The channel can hold this many messages before all senders are parked.
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    /// Returns the number of senders in existence for this channel.
+    pub fn num_senders(&self) -> usize {
+        self.inner.num_senders.load(SeqCst)
+    }
+
+    /// Returns the number of senders currently parked, waiting for the
+    /// receiver to make room in the channel.
+    pub fn num_parked_senders(&self) -> usize {
+        self.inner.num_parked.load(SeqCst)
+    }
+
+    /// Polls for a batch of messages, appending up to `limit` of them to
+    /// `buf`.
+    ///
+    /// Every message in the channel is received at once, up to `limit`, and
+    /// as many parked senders are unparked. This is cheaper than receiving the
+    /// messages one at a time through `poll_next`.
+    ///
+    /// # Return value
+    ///
+    /// This method returns:
+    ///
+    /// - `Poll::Ready(n)` with `n > 0` if `n` messages were appended to `buf`;
+    /// - `Poll::Ready(0)` if the channel is closed and has no messages left,
+    ///   or if `limit` is zero;
+    /// - `Poll::Pending` if the channel is empty, in which case the current
+    ///   task is scheduled to be notified once a message is sent.
+    pub fn poll_recv_many(
+        &mut self,
+        cx: &mut task::Context,
+        buf: &mut Vec<T>,
+        limit: usize,
+    ) -> Poll<usize> {
+        if limit == 0 {
+            return Poll::Ready(0);
+        }
+
+        loop {
+            let n = self.next_messages(buf, limit);
+            if n > 0 {
+                return Poll::Ready(n);
+            }
+
+            // Same as in `poll_next`
+            match self.try_park(cx) {
+                TryPark::Parked => return Poll::Pending,
+                TryPark::Closed => return Poll::Ready(0),
+                TryPark::NotEmpty => continue,
+            }
+        }
+    }
+
+    /// Creates a future which receives a batch of up to `limit` messages into
+    /// `buf`, resolving to the number of messages received.
+    ///
+    /// See [`poll_recv_many`](Receiver::poll_recv_many) for details.
+    pub fn recv_many<'a>(&'a mut self, buf: &'a mut Vec<T>, limit: usize) -> RecvMany<'a, T> {
+        RecvMany {
+            receiver: self,
+            buf,
+            limit,
+        }
+    }
+
     /// Tries to receive the next message without notifying a context if empty.
     ///
     /// It is not recommended to call this function from inside of a future,
@@ -864,10 +1158,13 @@ impl<T> Receiver<T> {
                 PopResult::Data(msg) => {
                     // If there are any parked task handles in the parked queue,
                     // pop one and unpark it.
-                    self.unpark_one();
+                    self.unpark(1);
 
-                    // Decrement number of messages
-                    self.dec_num_messages();
+                    // Decrement number of messages. The `None` closing the
+                    // channel was never counted.
+                    if msg.is_some() {
+                        self.dec_num_messages(1);
+                    }
 
                     return Poll::Ready(msg);
                 }
@@ -893,20 +1190,50 @@ impl<T> Receiver<T> {
         }
     }
 
-    // Unpark a single task handle if there is one pending in the parked queue
-    fn unpark_one(&mut self) {
-        loop {
+    // Pop up to `limit` messages off the queue into `buf`, returning how many
+    // were received
+    fn next_messages(&mut self, buf: &mut Vec<T>, limit: usize) -> usize {
+        let mut n = 0;
+
+        while n < limit {
+            match unsafe { self.inner.message_queue.pop() } {
+                PopResult::Data(Some(msg)) => {
+                    buf.push(msg);
+                    n += 1;
+                }
+                // The senders closed the channel, this is the last message.
+                // `try_park` reports the channel as closed from now on.
+                PopResult::Data(None) => break,
+                PopResult::Empty => break,
+                // Same as in `next_message`
+                PopResult::Inconsistent => thread::yield_now(),
+            }
+        }
+
+        if n > 0 {
+            // Unpark the senders and decrement the number of messages for the
+            // whole batch at once
+            self.unpark(n);
+            self.dec_num_messages(n);
+        }
+
+        n
+    }
+
+    // Unpark up to `n` task handles pending in the parked queue
+    fn unpark(&mut self, mut n: usize) {
+        while n > 0 {
             match unsafe { self.inner.parked_queue.pop() } {
                 PopResult::Data(task) => {
-                    task.lock().unwrap().notify();
-                    return;
+                    self.inner.unpark_sender(&task);
+                    n -= 1;
                 }
                 PopResult::Empty => {
                     // Queue empty, no task to wake up.
                     return;
                 }
                 PopResult::Inconsistent => {
-                    // Same as above
+                    // Same as in `next_message`
                     thread::yield_now();
                 }
             }
@@ -936,13 +1263,13 @@ impl<T> Receiver<T> {
         TryPark::Parked
     }
 
-    fn dec_num_messages(&self) {
+    fn dec_num_messages(&self, n: usize) {
         let mut curr = self.inner.state.load(SeqCst);
 
         loop {
             let mut state = decode_state(curr);
 
-            state.num_messages -= 1;
+            state.num_messages -= n;
 
             let next = encode_state(&state);
             match self.inner.state.compare_exchange(curr, next, SeqCst, SeqCst) {
@@ -1024,6 +1351,42 @@ impl<T> UnboundedReceiver<T> {
     pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
         self.0.try_next()
     }
+
+    /// Polls for a batch of messages, appending up to `limit` of them to
+    /// `buf`.
+    ///
+    /// See [`Receiver::poll_recv_many`](Receiver::poll_recv_many) for details.
+    pub fn poll_recv_many(
+        &mut self,
+        cx: &mut task::Context,
+        buf: &mut Vec<T>,
+        limit: usize,
+    ) -> Poll<usize> {
+        self.0.poll_recv_many(cx, buf, limit)
+    }
+
+    /// Creates a future which receives a batch of up to `limit` messages into
+    /// `buf`, resolving to the number of messages received.
+    ///
+    /// See [`Receiver::poll_recv_many`](Receiver::poll_recv_many) for details.
+    pub fn recv_many<'a>(&'a mut self, buf: &'a mut Vec<T>, limit: usize) -> RecvMany<'a, T> {
+        self.0.recv_many(buf, limit)
+    }
+
+    /// Returns the number of messages waiting in the channel.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether there are no messages waiting in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of senders in existence for this channel.
+    pub fn num_senders(&self) -> usize {
+        self.0.num_senders()
+    }
 }
 
 impl<T> Stream for UnboundedReceiver<T> {
@@ -1037,6 +1400,21 @@ impl<T> Stream for UnboundedReceiver<T> {
     }
 }
 
+/*
+ *
+ * ===== impl RecvMany =====
+ *
+ */
+
+impl<'a, T> Future for RecvMany<'a, T> {
+    type Output = usize;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<usize> {
+        let this = &mut *self;
+        this.receiver.poll_recv_many(cx, this.buf, this.limit)
+    }
+}
+
 /*
  *
  * ===== impl Inner =====
@@ -1052,6 +1430,26 @@ impl<T> Inner<T> {
             None => MAX_BUFFER,
         }
     }
+
+    fn len(&self) -> usize {
+        decode_state(self.state.load(SeqCst)).num_messages
+    }
+
+    // Only meaningful for bounded channels
+    fn capacity(&self) -> usize {
+        let buffer = self.buffer.expect("unbounded channels have no capacity");
+        buffer + self.num_senders.load(SeqCst)
+    }
+
+    // Unpark a sender whose handle was popped off the parked queue. The
+    // sender may have unparked itself already.
+    fn unpark_sender(&self, task: &Mutex<SenderTask>) {
+        let mut task = task.lock().unwrap();
+        if task.is_parked {
+            self.num_parked.fetch_sub(1, SeqCst);
+        }
+        task.notify();
+    }
 }
 
 unsafe impl<T: Send> Send for Inner<T> {}
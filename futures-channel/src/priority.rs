@@ -0,0 +1,451 @@
+//! A multi-producer, single-consumer priority queue for sending values across
+//! asynchronous tasks.
+//!
+//! This channel behaves like the [`mpsc`](crate::mpsc) channel, except that
+//! every message is sent along with a priority. The [`Receiver`] always yields
+//! the message with the highest priority among those waiting in the channel.
+//! Messages of equal priority are received in the order they were sent.
+//!
+//! [`Receiver`] implements [`Stream`] and [`Sender`] implements the `Sink`
+//! trait, taking `(priority, message)` pairs. If the channel is at capacity,
+//! the send will be rejected and the task will be notified when additional
+//! capacity is available. In other words, the channel provides backpressure.
+//!
+//! # Disconnection
+//!
+//! When all [`Sender`] handles have been dropped, it is no longer possible to
+//! send values into the channel. Once the remaining messages have been
+//! received, the receiver's stream terminates.
+//!
+//! If the [`Receiver`] is dropped, then messages can no longer be read out of
+//! the channel. In this case, all further attempts to send will result in an
+//! error.
+//!
+//! [`Sender`]: struct.Sender.html
+//! [`Receiver`]: struct.Receiver.html
+//! [`Stream`]: ../../futures_core/stream/trait.Stream.html
+
+// The channel follows the same protocol as the `mpmc` channel, with a single
+// receiver: the capacity is `buffer + num-senders`, and a sender which pushes
+// a message while the channel holds more than `buffer` messages parks itself.
+// Each message received pops one handle off the parked task queue, whatever
+// the message's priority.
+
+use futures_core::stream::Stream;
+use futures_core::task::{self, Poll, Waker};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::marker::Unpin;
+use std::pin::PinMut;
+use std::sync::{Arc, Mutex};
+use std::usize;
+use std::vec::Vec;
+
+use crate::mpsc::SenderTask;
+
+pub use crate::mpsc::{SendError, TryRecvError, TrySendError};
+
+/// The transmission end of a priority channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Sender<P, T> {
+    // Channel state shared between the senders and the receiver.
+    inner: Arc<Inner<P, T>>,
+
+    // Handle to the task that is blocked on this sender. This handle is sent
+    // to the receiver in order to be notified when the sender becomes
+    // unblocked.
+    sender_task: Arc<Mutex<SenderTask>>,
+
+    // True if the sender might be blocked. This is an optimization to avoid
+    // having to lock the mutex most of the time.
+    maybe_parked: bool,
+}
+
+/// The receiving end of a priority channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Receiver<P, T> {
+    inner: Arc<Inner<P, T>>,
+}
+
+// We never project PinMut<Sender> or PinMut<Receiver> to `PinMut<T>`
+impl<P, T> Unpin for Sender<P, T> {}
+impl<P, T> Unpin for Receiver<P, T> {}
+
+#[derive(Debug)]
+struct Inner<P, T> {
+    // Max buffer size of the channel
+    buffer: usize,
+
+    state: Mutex<State<P, T>>,
+}
+
+#[derive(Debug)]
+struct State<P, T> {
+    // `false` once the receiver closed the channel or is gone
+    is_open: bool,
+
+    // The messages waiting to be received, highest priority first
+    message_queue: BinaryHeap<Entry<P, T>>,
+
+    // Sequence number of the next message sent, used to keep messages of
+    // equal priority in FIFO order
+    next_seq: u64,
+
+    // FIFO queue of parked task handles, popped once per message received
+    parked_queue: VecDeque<Arc<Mutex<SenderTask>>>,
+
+    // Number of senders in existence
+    num_senders: usize,
+
+    // Handle to the receiver's task
+    recv_task: Option<Waker>,
+}
+
+#[derive(Debug)]
+struct Entry<P, T> {
+    priority: P,
+    seq: u64,
+    msg: T,
+}
+
+// `BinaryHeap` is a max-heap: entries with a higher priority compare greater,
+// and among equal priorities the entry sent first does.
+impl<P: Ord, T> Ord for Entry<P, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl<P: Ord, T> PartialOrd for Entry<P, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<P: Ord, T> PartialEq for Entry<P, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<P: Ord, T> Eq for Entry<P, T> {}
+
+/// Creates a bounded priority channel for communicating between asynchronous
+/// tasks.
+///
+/// As with [`mpsc::channel`](crate::mpsc::channel), the channel's capacity is
+/// equal to `buffer + num-senders`.
+///
+/// The [`Receiver`](Receiver) returned implements the
+/// [`Stream`](futures_core::stream::Stream) trait, while [`Sender`](Sender)
+/// implements `Sink`.
+pub fn channel<P: Ord, T>(buffer: usize) -> (Sender<P, T>, Receiver<P, T>) {
+    // Check that the requested buffer size does not exceed the maximum buffer
+    // size permitted by the system.
+    assert!(buffer < usize::MAX >> 1, "requested buffer size too large");
+
+    let inner = Arc::new(Inner {
+        buffer,
+        state: Mutex::new(State {
+            is_open: true,
+            message_queue: BinaryHeap::new(),
+            next_seq: 0,
+            parked_queue: VecDeque::new(),
+            num_senders: 1,
+            recv_task: None,
+        }),
+    });
+
+    let tx = Sender {
+        inner: inner.clone(),
+        sender_task: Arc::new(Mutex::new(SenderTask::new())),
+        maybe_parked: false,
+    };
+
+    let rx = Receiver {
+        inner,
+    };
+
+    (tx, rx)
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<P: Ord, T> Sender<P, T> {
+    /// Attempts to send a message with the given priority on this `Sender`,
+    /// returning them if there was an error.
+    pub fn try_send(&mut self, priority: P, msg: T) -> Result<(), TrySendError<(P, T)>> {
+        // If the sender is currently blocked, reject the message
+        if !self.poll_unparked(None).is_ready() {
+            return Err(TrySendError::new(SendError::full(), (priority, msg)));
+        }
+
+        // The channel has capacity to accept the message, so send it
+        self.do_send(priority, msg)
+    }
+
+    /// Send a message with the given priority on the channel.
+    ///
+    /// This function should only be called after
+    /// [`poll_ready`](Sender::poll_ready) has reported that the channel is
+    /// ready to receive a message.
+    pub fn start_send(&mut self, priority: P, msg: T) -> Result<(), SendError> {
+        self.try_send(priority, msg)
+            .map_err(|e| e.into_send_error())
+    }
+
+    fn do_send(&mut self, priority: P, msg: T) -> Result<(), TrySendError<(P, T)>> {
+        let (park_self, recv_task) = {
+            let mut state = self.inner.state.lock().unwrap();
+
+            if !state.is_open {
+                return Err(TrySendError::new(SendError::disconnected(), (priority, msg)));
+            }
+
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.message_queue.push(Entry { priority, seq, msg });
+
+            // Park if the number of pending messages has exceeded the
+            // configured buffer size. The task is registered by `poll_ready`
+            // once the sender is polled.
+            let park_self = state.message_queue.len() > self.inner.buffer;
+            if park_self {
+                self.sender_task.lock().unwrap().is_parked = true;
+                state.parked_queue.push_back(self.sender_task.clone());
+            }
+
+            (park_self, state.recv_task.take())
+        };
+
+        if park_self {
+            self.maybe_parked = true;
+        }
+
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+
+        Ok(())
+    }
+}
+
+impl<P, T> Sender<P, T> {
+    /// Polls the channel to determine if there is guaranteed capacity to send
+    /// at least one item without waiting.
+    ///
+    /// # Return value
+    ///
+    /// This method returns:
+    ///
+    /// - `Poll::Ready(Ok(_))` if there is sufficient capacity;
+    /// - `Poll::Pending` if the channel may not have capacity, in which case
+    ///   the current task is queued to be notified once capacity is available;
+    /// - `Poll::Ready(Err(SendError))` if the receiver has been dropped.
+    pub fn poll_ready(
+        &mut self,
+        cx: &mut task::Context
+    ) -> Poll<Result<(), SendError>> {
+        if self.is_closed() {
+            return Poll::Ready(Err(SendError::disconnected()));
+        }
+
+        self.poll_unparked(Some(cx)).map(Ok)
+    }
+
+    /// Returns whether this channel is closed without needing a context.
+    pub fn is_closed(&self) -> bool {
+        !self.inner.state.lock().unwrap().is_open
+    }
+
+    /// Closes this channel from the sender side, preventing any new messages.
+    pub fn close_channel(&mut self) {
+        self.inner.close();
+    }
+
+    fn poll_unparked(&mut self, cx: Option<&mut task::Context>) -> Poll<()> {
+        // First check the `maybe_parked` variable. This avoids acquiring the
+        // lock in most cases
+        if self.maybe_parked {
+            // Get a lock on the task handle
+            let mut task = self.sender_task.lock().unwrap();
+
+            if !task.is_parked {
+                self.maybe_parked = false;
+                return Poll::Ready(())
+            }
+
+            // At this point, an unpark request is pending, so there will be an
+            // unpark sometime in the future. We just need to make sure that
+            // the correct task will be notified.
+            //
+            // Update the task in case the `Sender` has been moved to another
+            // task
+            task.task = cx.map(|cx| cx.waker().clone());
+
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+impl<P, T> Clone for Sender<P, T> {
+    fn clone(&self) -> Sender<P, T> {
+        self.inner.state.lock().unwrap().num_senders += 1;
+
+        Sender {
+            inner: self.inner.clone(),
+            sender_task: Arc::new(Mutex::new(SenderTask::new())),
+            maybe_parked: false,
+        }
+    }
+}
+
+impl<P, T> Drop for Sender<P, T> {
+    fn drop(&mut self) {
+        let recv_task = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.num_senders -= 1;
+
+            if state.num_senders > 0 {
+                return;
+            }
+
+            // The receiver needs to be woken up to observe the end of the
+            // stream.
+            state.recv_task.take()
+        };
+
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<P, T> Receiver<P, T> {
+    /// Closes the receiving half of the channel, without dropping it.
+    ///
+    /// This prevents any further messages from being sent on the channel while
+    /// still enabling the receiver to drain messages that are buffered.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+}
+
+impl<P: Ord, T> Receiver<P, T> {
+    /// Tries to receive the next message without notifying a context if empty.
+    ///
+    /// It is not recommended to call this function from inside of a future,
+    /// only when you've otherwise arranged to be notified when the channel is
+    /// no longer empty.
+    pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
+        match self.next_message(None) {
+            Poll::Ready(msg) => Ok(msg),
+            Poll::Pending => Err(TryRecvError::new()),
+        }
+    }
+
+    fn next_message(&mut self, cx: Option<&mut task::Context>) -> Poll<Option<T>> {
+        let (entry, sender_task) = {
+            let mut state = self.inner.state.lock().unwrap();
+
+            match state.message_queue.pop() {
+                Some(entry) => (entry, state.parked_queue.pop_front()),
+                None => {
+                    if !state.is_open || state.num_senders == 0 {
+                        return Poll::Ready(None);
+                    }
+
+                    if let Some(cx) = cx {
+                        state.recv_task = Some(cx.waker().clone());
+                    }
+                    return Poll::Pending;
+                }
+            }
+        };
+
+        // If there was a parked sender, unpark it as a slot has become
+        // available.
+        if let Some(task) = sender_task {
+            task.lock().unwrap().notify();
+        }
+
+        Poll::Ready(Some(entry.msg))
+    }
+}
+
+impl<P: Ord, T> Stream for Receiver<P, T> {
+    type Item = T;
+
+    fn poll_next(
+        mut self: PinMut<Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Option<T>> {
+        self.next_message(Some(cx))
+    }
+}
+
+impl<P, T> Drop for Receiver<P, T> {
+    fn drop(&mut self) {
+        let (messages, sender_tasks) = {
+            let mut state = match self.inner.state.lock() {
+                Ok(state) => state,
+                Err(_) => return,
+            };
+
+            state.is_open = false;
+            let messages = state.message_queue.drain().collect::<Vec<_>>();
+            let sender_tasks = state.parked_queue.drain(..).collect::<Vec<_>>();
+            (messages, sender_tasks)
+        };
+
+        // The messages are dropped once the lock is released, then the
+        // senders are unparked to observe the disconnection.
+        drop(messages);
+        for task in sender_tasks {
+            task.lock().unwrap().notify();
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Inner =====
+ *
+ */
+
+impl<P, T> Inner<P, T> {
+    fn close(&self) {
+        let (recv_task, sender_tasks) = {
+            let mut state = self.state.lock().unwrap();
+            state.is_open = false;
+            let sender_tasks = state.parked_queue.drain(..).collect::<Vec<_>>();
+            (state.recv_task.take(), sender_tasks)
+        };
+
+        // Wake up any tasks waiting as they'll see that we've closed the
+        // channel and will continue on their merry way.
+        for task in sender_tasks {
+            task.lock().unwrap().notify();
+        }
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+    }
+}
@@ -0,0 +1,402 @@
+//! A multi-producer, single-consumer channel without any buffering.
+//!
+//! Unlike an [`mpsc`](crate::mpsc) channel created with a `buffer` of zero,
+//! which still holds one message per sender, a rendezvous channel hands each
+//! message over directly: flushing a [`Sender`] completes only once the
+//! [`Receiver`] has taken the message that was sent. In particular, the future
+//! returned by `SinkExt::send` resolves only after the receiver has received
+//! the item.
+//!
+//! The channel holds at most one message at a time, which a sender places in
+//! it with `start_send`. Other senders wait in `poll_ready` until that message
+//! has been received.
+//!
+//! # Disconnection
+//!
+//! When all [`Sender`] handles have been dropped, the [`Receiver`]'s stream
+//! terminates once it has taken the message left in the channel, if any.
+//!
+//! If the [`Receiver`] is dropped, then the message in the channel is dropped
+//! and all further attempts to send or flush result in an error.
+//!
+//! [`Sender`]: struct.Sender.html
+//! [`Receiver`]: struct.Receiver.html
+
+use futures_core::stream::Stream;
+use futures_core::task::{self, Poll, Waker};
+use std::marker::Unpin;
+use std::pin::PinMut;
+use std::sync::{Arc, Mutex};
+
+use crate::wakers::Wakers;
+
+pub use crate::mpsc::{SendError, TryRecvError, TrySendError};
+
+/// The transmission end of a rendezvous channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+
+    // Key of the slot holding this sender's task in `State::send_tasks`.
+    key: usize,
+
+    // Sequence number of the message this sender is waiting to be received,
+    // if any.
+    ticket: Option<u64>,
+}
+
+/// The receiving end of a rendezvous channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+}
+
+// We never project PinMut<Sender> or PinMut<Receiver> to `PinMut<T>`
+impl<T> Unpin for Sender<T> {}
+impl<T> Unpin for Receiver<T> {}
+
+#[derive(Debug)]
+struct Inner<T> {
+    state: Mutex<State<T>>,
+}
+
+#[derive(Debug)]
+struct State<T> {
+    // The message waiting to be received
+    slot: Option<T>,
+
+    // Number of messages placed in the slot so far. Doubles as the sequence
+    // number of the last message sent.
+    sent: u64,
+
+    // Number of messages taken out of the slot so far
+    taken: u64,
+
+    // `false` once the channel was closed from either side
+    is_open: bool,
+
+    // Number of senders in existence
+    num_senders: usize,
+
+    // Tasks of the senders waiting for the slot to be emptied
+    send_tasks: Wakers,
+
+    // Task of the receiver waiting for a message
+    recv_task: Option<Waker>,
+}
+
+/// Creates a rendezvous channel for communicating between asynchronous tasks.
+///
+/// The [`Receiver`](Receiver) returned implements the
+/// [`Stream`](futures_core::stream::Stream) trait, while [`Sender`](Sender)
+/// implements `Sink`.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let mut send_tasks = Wakers::new();
+    let key = send_tasks.insert();
+
+    let inner = Arc::new(Inner {
+        state: Mutex::new(State {
+            slot: None,
+            sent: 0,
+            taken: 0,
+            is_open: true,
+            num_senders: 1,
+            send_tasks,
+            recv_task: None,
+        }),
+    });
+
+    let tx = Sender {
+        inner: inner.clone(),
+        key,
+        ticket: None,
+    };
+    let rx = Receiver {
+        inner,
+    };
+
+    (tx, rx)
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T> Sender<T> {
+    /// Polls the channel to determine if a message can be placed in it.
+    ///
+    /// # Return value
+    ///
+    /// This method returns:
+    ///
+    /// - `Poll::Ready(Ok(_))` if the channel is empty;
+    /// - `Poll::Pending` if the channel holds a message that hasn't been
+    ///   received yet, in which case the current task is queued to be notified
+    ///   once it has been;
+    /// - `Poll::Ready(Err(SendError))` if the channel has been closed.
+    pub fn poll_ready(
+        &mut self,
+        cx: &mut task::Context,
+    ) -> Poll<Result<(), SendError>> {
+        let mut state = self.inner.state.lock().unwrap();
+
+        if !state.is_open {
+            return Poll::Ready(Err(SendError::disconnected()));
+        }
+
+        if state.slot.is_some() {
+            state.send_tasks.register(self.key, cx.waker());
+            return Poll::Pending;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Attempts to place a message in the channel, returning the message if
+    /// there was an error.
+    ///
+    /// Success only means that the message is waiting to be received, use
+    /// [`poll_flush`](Sender::poll_flush) to learn when it has been.
+    pub fn try_send(&mut self, msg: T) -> Result<(), TrySendError<T>> {
+        let recv_task = {
+            let mut state = self.inner.state.lock().unwrap();
+
+            if !state.is_open {
+                return Err(TrySendError::new(SendError::disconnected(), msg));
+            }
+
+            if state.slot.is_some() {
+                return Err(TrySendError::new(SendError::full(), msg));
+            }
+
+            state.slot = Some(msg);
+            state.sent += 1;
+            self.ticket = Some(state.sent);
+            state.recv_task.take()
+        };
+
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+
+        Ok(())
+    }
+
+    /// Send a message on the channel.
+    ///
+    /// This function should only be called after
+    /// [`poll_ready`](Sender::poll_ready) has reported that the channel is
+    /// ready to receive a message.
+    pub fn start_send(&mut self, msg: T) -> Result<(), SendError> {
+        self.try_send(msg)
+            .map_err(|e| e.into_send_error())
+    }
+
+    /// Polls for the last message sent by this sender to be taken by the
+    /// receiver.
+    ///
+    /// # Return value
+    ///
+    /// This method returns:
+    ///
+    /// - `Poll::Ready(Ok(_))` if the message has been received, or if no
+    ///   message was sent;
+    /// - `Poll::Pending` if the message is still in the channel, in which case
+    ///   the current task is queued to be notified once it has been received;
+    /// - `Poll::Ready(Err(SendError))` if the receiver was dropped before it
+    ///   received the message.
+    pub fn poll_flush(
+        &mut self,
+        cx: &mut task::Context,
+    ) -> Poll<Result<(), SendError>> {
+        let ticket = match self.ticket {
+            Some(ticket) => ticket,
+            None => return Poll::Ready(Ok(())),
+        };
+
+        let mut state = self.inner.state.lock().unwrap();
+
+        if state.taken >= ticket {
+            self.ticket = None;
+            return Poll::Ready(Ok(()));
+        }
+
+        // The message is still in the slot unless the receiver has dropped it
+        // on its way out.
+        if state.slot.is_none() {
+            self.ticket = None;
+            return Poll::Ready(Err(SendError::disconnected()));
+        }
+
+        state.send_tasks.register(self.key, cx.waker());
+        Poll::Pending
+    }
+
+    /// Returns whether this channel is closed without needing a context.
+    pub fn is_closed(&self) -> bool {
+        !self.inner.state.lock().unwrap().is_open
+    }
+
+    /// Closes this channel from the sender side, preventing any new messages.
+    ///
+    /// A message already in the channel can still be received.
+    pub fn close_channel(&mut self) {
+        self.inner.close();
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        let mut state = self.inner.state.lock().unwrap();
+        state.num_senders += 1;
+
+        Sender {
+            inner: self.inner.clone(),
+            key: state.send_tasks.insert(),
+            ticket: None,
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let recv_task = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.num_senders -= 1;
+            state.send_tasks.remove(self.key);
+
+            if state.num_senders > 0 {
+                return;
+            }
+
+            // The receiver needs to be woken up to observe the end of the
+            // stream.
+            state.recv_task.take()
+        };
+
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T> Receiver<T> {
+    /// Closes the receiving half of the channel, without dropping it.
+    ///
+    /// This prevents any further messages from being sent on the channel while
+    /// still enabling the receiver to take a message that was already sent.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    /// Tries to receive the next message without notifying a context if empty.
+    ///
+    /// It is not recommended to call this function from inside of a future,
+    /// only when you've otherwise arranged to be notified when the channel is
+    /// no longer empty.
+    pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
+        match self.next_message(None) {
+            Poll::Ready(msg) => Ok(msg),
+            Poll::Pending => Err(TryRecvError::new()),
+        }
+    }
+
+    fn next_message(&mut self, cx: Option<&mut task::Context>) -> Poll<Option<T>> {
+        let (msg, send_tasks) = {
+            let mut state = self.inner.state.lock().unwrap();
+
+            match state.slot.take() {
+                Some(msg) => {
+                    state.taken += 1;
+                    (msg, state.send_tasks.take_all())
+                }
+                None => {
+                    if !state.is_open || state.num_senders == 0 {
+                        return Poll::Ready(None);
+                    }
+
+                    if let Some(cx) = cx {
+                        state.recv_task = Some(cx.waker().clone());
+                    }
+                    return Poll::Pending;
+                }
+            }
+        };
+
+        // Wake up the sender waiting for the message to be received, along
+        // with the senders waiting for the slot to be emptied.
+        for task in send_tasks {
+            task.wake();
+        }
+
+        Poll::Ready(Some(msg))
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(
+        mut self: PinMut<Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Option<T>> {
+        self.next_message(Some(cx))
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let (msg, send_tasks) = {
+            let mut state = match self.inner.state.lock() {
+                Ok(state) => state,
+                Err(_) => return,
+            };
+            state.is_open = false;
+            (state.slot.take(), state.send_tasks.take_all())
+        };
+
+        // Drop the message that will never be received outside of the lock,
+        // then let the senders observe the disconnection.
+        drop(msg);
+        for task in send_tasks {
+            task.wake();
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Inner =====
+ *
+ */
+
+impl<T> Inner<T> {
+    fn close(&self) {
+        let (send_tasks, recv_task) = {
+            let mut state = self.state.lock().unwrap();
+            state.is_open = false;
+            (state.send_tasks.take_all(), state.recv_task.take())
+        };
+
+        // Wake up any tasks waiting as they'll see that we've closed the
+        // channel and will continue on their merry way.
+        for task in send_tasks {
+            task.wake();
+        }
+        if let Some(task) = recv_task {
+            task.wake();
+        }
+    }
+}
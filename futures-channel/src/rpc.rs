@@ -0,0 +1,290 @@
+//! A request/response channel for calling into a server task.
+//!
+//! Channel creation provides a [`Client`] and a [`Server`] handle. Each
+//! [`Client::call`] sends a request to the server and returns a future
+//! resolving to the response. [`Server`] implements [`Stream`], yielding each
+//! request along with a [`Responder`] used to send the response back to the
+//! caller.
+//!
+//! Requests are sent over an [`mpsc`](crate::mpsc) channel, so calls wait for
+//! the server to catch up once too many requests are waiting for it. Each
+//! response is sent back over a [`oneshot`](crate::oneshot) channel.
+//!
+//! Any number of calls can be in flight on a single [`Client`] at once. They
+//! share the client's handle on the request channel, so they wait for the
+//! server the same way as messages sent from a single `mpsc::Sender`.
+//!
+//! # Cancellation
+//!
+//! A caller which drops the future returned by [`Client::call`] before it
+//! completes gives up on the response. The server can observe this through
+//! [`Responder::poll_cancel`] and stop working on the request.
+//!
+//! If the [`Server`] is dropped, or drops a [`Responder`] without responding,
+//! the pending calls resolve to [`Canceled`].
+//!
+//! [`Client`]: struct.Client.html
+//! [`Client::call`]: struct.Client.html#method.call
+//! [`Server`]: struct.Server.html
+//! [`Responder`]: struct.Responder.html
+//! [`Responder::poll_cancel`]: struct.Responder.html#method.poll_cancel
+//! [`Canceled`]: ../oneshot/struct.Canceled.html
+//! [`Stream`]: ../../futures_core/stream/trait.Stream.html
+
+use futures_core::future::Future;
+use futures_core::stream::Stream;
+use futures_core::task::{self, Poll, Wake, local_waker_from_nonlocal};
+use std::marker::Unpin;
+use std::pin::PinMut;
+use std::sync::{Arc, Mutex};
+
+use crate::mpsc;
+use crate::oneshot::{self, Canceled};
+use crate::wakers::Wakers;
+
+/// The calling end of a request/response channel.
+///
+/// This value is created by the [`channel`](channel) function. Calls only
+/// borrow the client for as long as [`call`](Client::call) runs, so a single
+/// client can make concurrent calls; clones of it can be sent to other tasks.
+#[derive(Debug)]
+pub struct Client<Req, Resp> {
+    shared: Arc<Shared<Req, Resp>>,
+}
+
+// State shared by a client and its pending calls
+#[derive(Debug)]
+struct Shared<Req, Resp> {
+    tx: Mutex<mpsc::Sender<(Req, oneshot::Sender<Resp>)>>,
+
+    // Tasks of the calls waiting for room in the channel. The sender only
+    // remembers the task of the last call which found it parked, so it is
+    // handed a waker for all of them instead.
+    waiting: Arc<CallTasks>,
+}
+
+#[derive(Debug)]
+struct CallTasks {
+    tasks: Mutex<Wakers>,
+}
+
+/// The serving end of a request/response channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Server<Req, Resp> {
+    rx: mpsc::Receiver<(Req, oneshot::Sender<Resp>)>,
+}
+
+/// A handle for responding to a request received by a [`Server`](Server).
+#[derive(Debug)]
+pub struct Responder<Resp> {
+    tx: oneshot::Sender<Resp>,
+}
+
+/// A future which sends a request and resolves to its response.
+///
+/// This value is created by the [`call`](Client::call) method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Call<Req, Resp> {
+    shared: Arc<Shared<Req, Resp>>,
+    request: Option<(Req, oneshot::Sender<Resp>)>,
+    response: oneshot::Receiver<Resp>,
+
+    // Key of the slot holding this call's task in `CallTasks`, while it waits
+    // for room in the channel
+    wait_key: Option<usize>,
+}
+
+// Pinning is never projected to the request or response
+impl<Req, Resp> Unpin for Client<Req, Resp> {}
+impl<Req, Resp> Unpin for Server<Req, Resp> {}
+impl<Req, Resp> Unpin for Call<Req, Resp> {}
+
+/// Creates a request/response channel.
+///
+/// Up to `buffer` requests can wait for the server before calls have to wait
+/// for the server to receive them, see [`mpsc::channel`](crate::mpsc::channel).
+/// As with a cloned `mpsc::Sender`, each client is also guaranteed a slot of
+/// its own, so the server may have up to `buffer` plus the number of clients
+/// requests waiting for it.
+///
+/// The [`Server`](Server) returned implements the
+/// [`Stream`](futures_core::stream::Stream) trait, while calls are made
+/// through the [`Client`](Client), which can be cloned.
+pub fn channel<Req, Resp>(buffer: usize) -> (Client<Req, Resp>, Server<Req, Resp>) {
+    let (tx, rx) = mpsc::channel(buffer);
+    (Client::new(tx), Server { rx })
+}
+
+/*
+ *
+ * ===== impl Client =====
+ *
+ */
+
+impl<Req, Resp> Client<Req, Resp> {
+    fn new(tx: mpsc::Sender<(Req, oneshot::Sender<Resp>)>) -> Client<Req, Resp> {
+        Client {
+            shared: Arc::new(Shared {
+                tx: Mutex::new(tx),
+                waiting: Arc::new(CallTasks {
+                    tasks: Mutex::new(Wakers::new()),
+                }),
+            }),
+        }
+    }
+
+    /// Sends `request` to the server, returning a future which resolves to the
+    /// response.
+    ///
+    /// The future resolves to [`Canceled`](crate::oneshot::Canceled) if the
+    /// server is gone or drops the request without responding. Dropping the
+    /// future cancels the call.
+    ///
+    /// The future doesn't borrow the client, and any number of calls can be
+    /// pending at once. Once the server falls behind, the calls wait for it to
+    /// receive the requests already sent before sending their own.
+    pub fn call(&self, request: Req) -> Call<Req, Resp> {
+        let (tx, rx) = oneshot::channel();
+        Call {
+            shared: self.shared.clone(),
+            request: Some((request, tx)),
+            response: rx,
+            wait_key: None,
+        }
+    }
+
+    /// Returns whether the server is gone, in which case all calls fail.
+    pub fn is_closed(&self) -> bool {
+        self.shared.tx.lock().unwrap().is_closed()
+    }
+}
+
+impl<Req, Resp> Clone for Client<Req, Resp> {
+    fn clone(&self) -> Client<Req, Resp> {
+        Client::new(self.shared.tx.lock().unwrap().clone())
+    }
+}
+
+impl Wake for CallTasks {
+    fn wake(arc_self: &Arc<Self>) {
+        // There's room in the channel again. Every waiting call is woken up to
+        // try sending its request, the ones which don't make it wait again.
+        let tasks = arc_self.tasks.lock().unwrap().take_all();
+        for task in tasks {
+            task.wake();
+        }
+    }
+}
+
+impl<Req, Resp> Call<Req, Resp> {
+    // Stops waiting for room in the channel
+    fn stop_waiting(&mut self) {
+        if let Some(key) = self.wait_key.take() {
+            self.shared.waiting.tasks.lock().unwrap().remove(key);
+        }
+    }
+}
+
+impl<Req, Resp> Future for Call<Req, Resp> {
+    type Output = Result<Resp, Canceled>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        if let Some(request) = this.request.take() {
+            // The task is registered before polling the sender, so that it
+            // isn't missed if the sender is unparked right away.
+            {
+                let mut tasks = this.shared.waiting.tasks.lock().unwrap();
+                let key = *this.wait_key.get_or_insert_with(|| tasks.insert());
+                tasks.register(key, cx.waker());
+            }
+
+            let local_waker = local_waker_from_nonlocal(this.shared.waiting.clone());
+            let mut tx = this.shared.tx.lock().unwrap();
+            match tx.poll_ready(&mut cx.with_waker(&local_waker)) {
+                Poll::Ready(Ok(())) => {
+                    // If the request can't be sent, the oneshot sender is
+                    // dropped along with it and the response below resolves
+                    // to `Canceled`.
+                    let _ = tx.start_send(request);
+                }
+                Poll::Ready(Err(_)) => drop(request),
+                Poll::Pending => {
+                    this.request = Some(request);
+                    return Poll::Pending;
+                }
+            }
+            drop(tx);
+            this.stop_waiting();
+        }
+
+        PinMut::new(&mut this.response).poll(cx)
+    }
+}
+
+impl<Req, Resp> Drop for Call<Req, Resp> {
+    fn drop(&mut self) {
+        self.stop_waiting();
+    }
+}
+
+/*
+ *
+ * ===== impl Server =====
+ *
+ */
+
+impl<Req, Resp> Server<Req, Resp> {
+    /// Closes the channel, without dropping the server.
+    ///
+    /// Further calls fail, while requests that were already sent can still be
+    /// received and responded to.
+    pub fn close(&mut self) {
+        self.rx.close();
+    }
+}
+
+impl<Req, Resp> Stream for Server<Req, Resp> {
+    type Item = (Req, Responder<Resp>);
+
+    fn poll_next(
+        mut self: PinMut<Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Option<Self::Item>> {
+        PinMut::new(&mut self.rx).poll_next(cx)
+            .map(|item| item.map(|(request, tx)| (request, Responder { tx })))
+    }
+}
+
+/*
+ *
+ * ===== impl Responder =====
+ *
+ */
+
+impl<Resp> Responder<Resp> {
+    /// Sends the response back to the caller.
+    ///
+    /// The response is returned as an error if the caller gave up on the
+    /// call.
+    pub fn respond(self, response: Resp) -> Result<(), Resp> {
+        self.tx.send(response)
+    }
+
+    /// Polls for the caller giving up on the call.
+    ///
+    /// Resolves once the future returned by [`call`](Client::call) has been
+    /// dropped, see [`oneshot::Sender::poll_cancel`](crate::oneshot::Sender::poll_cancel).
+    pub fn poll_cancel(&mut self, cx: &mut task::Context) -> Poll<()> {
+        self.tx.poll_cancel(cx)
+    }
+
+    /// Returns whether the caller gave up on the call.
+    pub fn is_canceled(&self) -> bool {
+        self.tx.is_canceled()
+    }
+}
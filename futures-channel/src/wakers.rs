@@ -0,0 +1,103 @@
+//! A keyed set of task handles.
+//!
+//! Channels with many handles on the same side (e.g. all the receivers of a
+//! broadcast channel) need to remember which task is blocked on each handle.
+//! Each handle reserves a slot when it is created and overwrites it whenever it
+//! needs to be notified, so a handle that is polled repeatedly never occupies
+//! more than one slot.
+//!
+//! `Wakers` does no synchronization of its own, it's expected to live behind
+//! the lock protecting the rest of the channel state.
+
+use futures_core::task::Waker;
+use std::mem;
+use std::vec::Vec;
+
+#[derive(Debug)]
+pub(crate) struct Wakers {
+    slots: Vec<Slot>,
+    // Keys of vacant slots, reused before the vector is grown.
+    free: Vec<usize>,
+}
+
+#[derive(Debug)]
+enum Slot {
+    Vacant,
+    Occupied(Option<Waker>),
+}
+
+impl Wakers {
+    pub(crate) fn new() -> Wakers {
+        Wakers {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Reserves a slot for a new handle, returning its key.
+    pub(crate) fn insert(&mut self) -> usize {
+        match self.free.pop() {
+            Some(key) => {
+                self.slots[key] = Slot::Occupied(None);
+                key
+            }
+            None => {
+                self.slots.push(Slot::Occupied(None));
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    /// Releases the slot of a handle that is going away, returning the task
+    /// that was registered in it, if any.
+    pub(crate) fn remove(&mut self, key: usize) -> Option<Waker> {
+        match mem::replace(&mut self.slots[key], Slot::Vacant) {
+            Slot::Occupied(waker) => {
+                self.free.push(key);
+                waker
+            }
+            Slot::Vacant => panic!("invalid waker key"),
+        }
+    }
+
+    /// Registers `waker` to be notified on behalf of the handle `key`,
+    /// replacing any previously registered task.
+    ///
+    /// Returns `true` if no task was registered for `key` before.
+    pub(crate) fn register(&mut self, key: usize, waker: &Waker) -> bool {
+        let slot = match &mut self.slots[key] {
+            Slot::Occupied(slot) => slot,
+            Slot::Vacant => panic!("invalid waker key"),
+        };
+        let (was_empty, needs_replacement) = match slot {
+            // Avoid the clone if the same task is registering again
+            Some(old) => (false, !old.will_wake(waker)),
+            None => (true, true),
+        };
+        if needs_replacement {
+            *slot = Some(waker.clone());
+        }
+        was_empty
+    }
+
+    /// Takes the task registered for the handle `key`, if any.
+    pub(crate) fn take(&mut self, key: usize) -> Option<Waker> {
+        match &mut self.slots[key] {
+            Slot::Occupied(slot) => slot.take(),
+            Slot::Vacant => panic!("invalid waker key"),
+        }
+    }
+
+    /// Takes every registered task, leaving the slots reserved.
+    ///
+    /// The tasks are returned rather than woken so that the caller can release
+    /// its lock first.
+    pub(crate) fn take_all(&mut self) -> Vec<Waker> {
+        self.slots.iter_mut()
+            .filter_map(|slot| match slot {
+                Slot::Occupied(waker) => waker.take(),
+                Slot::Vacant => None,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,319 @@
+//! A single-producer, multi-consumer channel that only retains the most
+//! recently sent value.
+//!
+//! Channel creation provides [`Sender`] and [`Receiver`] handles, along with
+//! the initial value of the channel. [`Sender::broadcast`] replaces the value
+//! retained by the channel. The current value can be inspected at any time
+//! with [`Receiver::borrow`].
+//!
+//! [`Receiver`] also implements [`Stream`], yielding the value of the channel
+//! each time it changes. Values are not queued: a receiver which is polled
+//! less often than values are broadcast only observes the latest one. The
+//! receiver can be cloned to create more handles to the same channel, each of
+//! which tracks the changes it has seen on its own.
+//!
+//! # Disconnection
+//!
+//! When the [`Sender`] is dropped, the stream of each receiver terminates
+//! once it has yielded the last value broadcast. [`Receiver::borrow`] keeps
+//! returning that value. If all receivers are dropped, further broadcasts
+//! fail.
+//!
+//! [`Sender`]: struct.Sender.html
+//! [`Receiver`]: struct.Receiver.html
+//! [`Sender::broadcast`]: struct.Sender.html#method.broadcast
+//! [`Receiver::borrow`]: struct.Receiver.html#method.borrow
+//! [`Stream`]: ../../futures_core/stream/trait.Stream.html
+
+use futures_core::stream::Stream;
+use futures_core::task::{self, Poll};
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::marker::Unpin;
+use std::mem;
+use std::ops::Deref;
+use std::pin::PinMut;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+
+use crate::wakers::Wakers;
+
+/// The sending half of a watch channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// The receiving half of a watch channel.
+///
+/// This value is created by the [`channel`](channel) function. Cloning it
+/// creates another handle to the same channel.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+
+    // Version of the last value yielded by this receiver's stream.
+    version: u64,
+
+    // Key of the slot holding this receiver's task in `State::wakers`.
+    key: usize,
+}
+
+// We never project PinMut<Sender> or PinMut<Receiver> to `PinMut<T>`
+impl<T> Unpin for Sender<T> {}
+impl<T> Unpin for Receiver<T> {}
+
+/// A reference to the value of a watch channel, returned by
+/// [`Receiver::borrow`](Receiver::borrow).
+///
+/// Broadcasting a new value blocks for as long as this reference is held.
+pub struct Ref<'a, T: 'a> {
+    guard: RwLockReadGuard<'a, Value<T>>,
+}
+
+/// The error type returned from [`broadcast`](Sender::broadcast) when all
+/// receivers have been dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("SendError")
+            .field(&"...")
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "send failed because all receivers are gone")
+    }
+}
+
+impl<T: Any> Error for SendError<T> {
+    fn description(&self) -> &str {
+        "send failed because all receivers are gone"
+    }
+}
+
+impl<T> SendError<T> {
+    /// Returns the value that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    value: RwLock<Value<T>>,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct Value<T> {
+    value: T,
+
+    // Incremented each time a value is broadcast.
+    version: u64,
+}
+
+#[derive(Debug)]
+struct State {
+    // `true` once the sender has been dropped
+    is_closed: bool,
+
+    // Number of receivers in existence
+    num_receivers: usize,
+
+    // Tasks of the receivers waiting for the value to change.
+    wakers: Wakers,
+}
+
+/// Creates a new watch channel, returning the sender and receiver halves.
+///
+/// The channel starts out holding `init`. The receiver's stream only yields
+/// values broadcast after its creation; the initial value is available through
+/// [`Receiver::borrow`](Receiver::borrow).
+pub fn channel<T>(init: T) -> (Sender<T>, Receiver<T>) {
+    let mut wakers = Wakers::new();
+    let key = wakers.insert();
+
+    let inner = Arc::new(Inner {
+        value: RwLock::new(Value {
+            value: init,
+            version: 0,
+        }),
+        state: Mutex::new(State {
+            is_closed: false,
+            num_receivers: 1,
+            wakers,
+        }),
+    });
+
+    let tx = Sender {
+        inner: inner.clone(),
+    };
+    let rx = Receiver {
+        inner,
+        version: 0,
+        key,
+    };
+
+    (tx, rx)
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T> Sender<T> {
+    /// Replaces the value of the channel and notifies every receiver of the
+    /// change.
+    ///
+    /// An error is returned with the value if all receivers have been dropped.
+    pub fn broadcast(&self, value: T) -> Result<(), SendError<T>> {
+        if self.is_closed() {
+            return Err(SendError(value));
+        }
+
+        // The old value is dropped once the lock is released
+        let _old = {
+            let mut current = self.inner.value.write().unwrap();
+            current.version += 1;
+            mem::replace(&mut current.value, value)
+        };
+
+        // The version must be updated before the wakers are taken, see
+        // `Receiver::poll_next`.
+        let wakers = self.inner.state.lock().unwrap().wakers.take_all();
+        for waker in wakers {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether all receivers have been dropped, in which case no value
+    /// can be broadcast anymore.
+    pub fn is_closed(&self) -> bool {
+        self.inner.state.lock().unwrap().num_receivers == 0
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let wakers = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.is_closed = true;
+            state.wakers.take_all()
+        };
+
+        // Wake up the receivers so they observe the end of the stream.
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T> Receiver<T> {
+    /// Returns a reference to the most recently broadcast value.
+    ///
+    /// This doesn't mark the value as seen: the stream still yields it if it
+    /// hasn't done so yet.
+    pub fn borrow(&self) -> Ref<T> {
+        Ref {
+            guard: self.inner.value.read().unwrap(),
+        }
+    }
+
+    /// Returns whether the sender has been dropped, in which case the value of
+    /// the channel won't change anymore.
+    pub fn is_closed(&self) -> bool {
+        self.inner.state.lock().unwrap().is_closed
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Receiver<T> {
+        let mut state = self.inner.state.lock().unwrap();
+        state.num_receivers += 1;
+
+        Receiver {
+            inner: self.inner.clone(),
+            version: self.version,
+            key: state.wakers.insert(),
+        }
+    }
+}
+
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(
+        mut self: PinMut<Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Option<T>> {
+        let this = &mut *self;
+
+        // The version is checked while holding the state lock, which the
+        // sender acquires after updating the value to take the wakers. Either
+        // the new version is seen here, or the task registered below is woken.
+        let mut state = this.inner.state.lock().unwrap();
+
+        {
+            let current = this.inner.value.read().unwrap();
+            if current.version != this.version {
+                this.version = current.version;
+                return Poll::Ready(Some(current.value.clone()));
+            }
+        }
+
+        if state.is_closed {
+            return Poll::Ready(None);
+        }
+
+        state.wakers.register(this.key, cx.waker());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.inner.state.lock() {
+            state.num_receivers -= 1;
+            state.wakers.remove(self.key);
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Ref =====
+ *
+ */
+
+impl<'a, T> Deref for Ref<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard.value
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for Ref<'a, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("Ref")
+            .field(&**self)
+            .finish()
+    }
+}
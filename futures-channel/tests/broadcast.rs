@@ -0,0 +1,138 @@
+#![feature(futures_api, arbitrary_self_types, pin)]
+
+use futures::channel::broadcast;
+use futures::executor::{block_on, block_on_stream};
+use futures::future::poll_fn;
+use futures::sink::SinkExt;
+use futures::stream::StreamExt;
+use futures::task::Poll;
+use std::thread;
+
+trait AssertSend: Send {}
+impl AssertSend for broadcast::Sender<i32> {}
+impl AssertSend for broadcast::Receiver<i32> {}
+
+#[test]
+fn send_recv() {
+    let (tx, rx) = broadcast::channel::<i32>(16);
+
+    assert_eq!(tx.send(1), Ok(1));
+    assert_eq!(tx.send(2), Ok(1));
+    drop(tx);
+
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![Ok(1), Ok(2)]);
+}
+
+#[test]
+fn every_receiver_gets_every_message() {
+    let (tx, rx1) = broadcast::channel::<i32>(16);
+    let rx2 = tx.subscribe();
+
+    assert_eq!(tx.send(1), Ok(2));
+    assert_eq!(tx.send(2), Ok(2));
+    drop(tx);
+
+    let v1: Vec<_> = block_on(rx1.collect());
+    let v2: Vec<_> = block_on(rx2.collect());
+    assert_eq!(v1, vec![Ok(1), Ok(2)]);
+    assert_eq!(v2, vec![Ok(1), Ok(2)]);
+}
+
+#[test]
+fn subscribe_only_sees_later_messages() {
+    let (tx, rx1) = broadcast::channel::<i32>(16);
+
+    tx.send(1).unwrap();
+    let rx2 = tx.subscribe();
+    tx.send(2).unwrap();
+    drop(tx);
+
+    let v1: Vec<_> = block_on(rx1.collect());
+    let v2: Vec<_> = block_on(rx2.collect());
+    assert_eq!(v1, vec![Ok(1), Ok(2)]);
+    assert_eq!(v2, vec![Ok(2)]);
+}
+
+#[test]
+fn slow_receiver_lags() {
+    let (tx, rx) = broadcast::channel::<i32>(2);
+    let mut rx = block_on_stream(rx);
+
+    for i in 0..5 {
+        tx.send(i).unwrap();
+    }
+
+    let err = rx.next().unwrap().unwrap_err();
+    assert_eq!(err.lagged(), 3);
+    assert_eq!(rx.next(), Some(Ok(3)));
+    assert_eq!(rx.next(), Some(Ok(4)));
+
+    drop(tx);
+    assert_eq!(rx.next(), None);
+}
+
+#[test]
+fn send_without_receivers_fails() {
+    let (tx, rx) = broadcast::channel::<i32>(1);
+    drop(rx);
+
+    assert_eq!(tx.receiver_count(), 0);
+    assert_eq!(tx.send(1).unwrap_err().into_inner(), 1);
+
+    // Receivers can come back at any time
+    let _rx = tx.subscribe();
+    assert_eq!(tx.send(2), Ok(1));
+}
+
+#[test]
+fn close_channel_ends_streams() {
+    let (tx, mut rx) = broadcast::channel::<i32>(4);
+
+    tx.send(1).unwrap();
+    tx.close_channel();
+    assert!(tx.is_closed());
+    assert!(tx.send(2).is_err());
+
+    block_on(poll_fn(move |cx| {
+        assert_eq!(rx.poll_next_unpin(cx), Poll::Ready(Some(Ok(1))));
+        assert_eq!(rx.poll_next_unpin(cx), Poll::Ready(None));
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn send_through_sink() {
+    let (mut tx, rx) = broadcast::channel::<i32>(4);
+
+    // `Sender::send` shadows `SinkExt::send`
+    block_on(SinkExt::send(&mut tx, 1)).unwrap();
+    block_on(tx.close()).unwrap();
+
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![Ok(1)]);
+}
+
+#[test]
+fn recv_threads() {
+    const N: i32 = 4;
+
+    let (tx, rx) = broadcast::channel::<i32>(N as usize);
+    let rx2 = tx.subscribe();
+
+    let threads = vec![rx, rx2].into_iter().map(|rx| {
+        thread::spawn(move || {
+            let v: Vec<_> = block_on(rx.collect());
+            assert_eq!(v, (0..N).map(Ok).collect::<Vec<_>>());
+        })
+    }).collect::<Vec<_>>();
+
+    for i in 0..N {
+        tx.send(i).unwrap();
+    }
+    drop(tx);
+
+    for t in threads {
+        t.join().unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+#![feature(futures_api, arbitrary_self_types, pin)]
+
+use futures::channel::lossy::{self, Overflow};
+use futures::executor::{block_on, block_on_stream};
+use futures::future::poll_fn;
+use futures::sink::SinkExt;
+use futures::stream::StreamExt;
+use futures::task::Poll;
+use std::thread;
+
+trait AssertSend: Send {}
+impl AssertSend for lossy::Sender<i32> {}
+impl AssertSend for lossy::Receiver<i32> {}
+
+#[test]
+fn send_recv() {
+    let (tx, rx) = lossy::channel::<i32>(4, Overflow::DropOldest);
+
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    drop(tx);
+
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![1, 2]);
+}
+
+#[test]
+fn drop_oldest() {
+    let (tx, rx) = lossy::channel::<i32>(2, Overflow::DropOldest);
+
+    for i in 0..5 {
+        tx.send(i).unwrap();
+    }
+    drop(tx);
+
+    assert_eq!(rx.dropped(), 3);
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![3, 4]);
+}
+
+#[test]
+fn drop_newest() {
+    let (tx, rx) = lossy::channel::<i32>(2, Overflow::DropNewest);
+
+    for i in 0..5 {
+        tx.send(i).unwrap();
+    }
+    drop(tx);
+
+    assert_eq!(rx.dropped(), 3);
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![0, 1]);
+}
+
+#[test]
+fn send_after_receiver_drop_fails() {
+    let (tx, rx) = lossy::channel::<i32>(1, Overflow::DropOldest);
+    drop(rx);
+
+    assert!(tx.is_closed());
+    assert_eq!(tx.send(1).unwrap_err().into_inner(), 1);
+}
+
+#[test]
+fn close_drains_remaining() {
+    let (mut tx, mut rx) = lossy::channel::<i32>(2, Overflow::DropOldest);
+
+    block_on(SinkExt::send(&mut tx, 1)).unwrap();
+    rx.close();
+    assert!(tx.send(2).is_err());
+
+    block_on(poll_fn(move |cx| {
+        assert_eq!(rx.poll_next_unpin(cx), Poll::Ready(Some(1)));
+        assert_eq!(rx.poll_next_unpin(cx), Poll::Ready(None));
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn send_recv_threads() {
+    const N: u64 = 1000;
+
+    let (tx, rx) = lossy::channel::<u64>(8, Overflow::DropOldest);
+
+    let t = thread::spawn(move || {
+        for i in 0..N {
+            tx.send(i).unwrap();
+        }
+    });
+
+    let mut rx = block_on_stream(rx);
+    let received = rx.by_ref().count() as u64;
+    t.join().unwrap();
+    assert_eq!(received + rx.into_inner().dropped(), N);
+}
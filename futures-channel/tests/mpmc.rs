@@ -0,0 +1,171 @@
+#![feature(futures_api, arbitrary_self_types, pin)]
+
+use futures::channel::mpmc;
+use futures::executor::{block_on, block_on_stream, LocalPool};
+use futures::future::poll_fn;
+use futures::stream::{Stream, StreamExt};
+use futures::sink::{Sink, SinkExt};
+use futures::task::{self, Poll, Wake};
+use pin_utils::pin_mut;
+use std::pin::PinMut;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+trait AssertSend: Send {}
+impl AssertSend for mpmc::Sender<i32> {}
+impl AssertSend for mpmc::Receiver<i32> {}
+
+#[test]
+fn send_recv() {
+    let (mut tx, rx) = mpmc::channel::<i32>(16);
+
+    block_on(tx.send(1)).unwrap();
+    drop(tx);
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![1]);
+}
+
+#[test]
+fn cloned_receivers_split_messages() {
+    let (tx, rx1) = mpmc::unbounded::<i32>();
+    let rx2 = rx1.clone();
+    let mut rx1 = block_on_stream(rx1);
+    let mut rx2 = block_on_stream(rx2);
+
+    for i in 0..4 {
+        tx.unbounded_send(i).unwrap();
+    }
+    drop(tx);
+
+    assert_eq!(rx1.next(), Some(0));
+    assert_eq!(rx2.next(), Some(1));
+    assert_eq!(rx2.next(), Some(2));
+    assert_eq!(rx1.next(), Some(3));
+    assert_eq!(rx1.next(), None);
+    assert_eq!(rx2.next(), None);
+}
+
+#[test]
+fn send_recv_no_buffer() {
+    // Run on a task context
+    block_on(poll_fn(move |cx| {
+        let (tx, rx) = mpmc::channel::<i32>(0);
+        pin_mut!(tx, rx);
+
+        assert!(tx.reborrow().poll_ready(cx).is_ready());
+
+        // Send first message
+        assert!(tx.reborrow().start_send(1).is_ok());
+        assert!(tx.reborrow().poll_ready(cx).is_pending());
+
+        // poll_ready said Pending, so no room in buffer, therefore new sends
+        // should get rejected with is_full.
+        assert!(tx.reborrow().start_send(0).unwrap_err().is_full());
+
+        // Take the value
+        assert_eq!(rx.reborrow().poll_next(cx), Poll::Ready(Some(1)));
+        assert!(tx.reborrow().poll_ready(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn receiver_done_waiting_is_not_woken() {
+    struct WakeCounter(AtomicUsize);
+
+    impl Wake for WakeCounter {
+        fn wake(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let counter1 = Arc::new(WakeCounter(AtomicUsize::new(0)));
+    let counter2 = Arc::new(WakeCounter(AtomicUsize::new(0)));
+    let waker1 = task::local_waker_from_nonlocal(counter1.clone());
+    let waker2 = task::local_waker_from_nonlocal(counter2.clone());
+    let pool = LocalPool::new();
+    let (mut spawn1, mut spawn2) = (pool.spawner(), pool.spawner());
+    let cx1 = &mut task::Context::new(&waker1, &mut spawn1);
+    let cx2 = &mut task::Context::new(&waker2, &mut spawn2);
+
+    let (tx, mut rx1) = mpmc::unbounded::<i32>();
+    let mut rx2 = rx1.clone();
+
+    // Both receivers wait, the first message wakes up the first one
+    assert!(PinMut::new(&mut rx1).poll_next(cx1).is_pending());
+    assert!(PinMut::new(&mut rx2).poll_next(cx2).is_pending());
+    tx.unbounded_send(1).unwrap();
+    assert_eq!(counter1.0.load(Ordering::SeqCst), 1);
+
+    // The second receiver takes the message without having been woken up,
+    // so the next message wakes up the first one again
+    assert_eq!(PinMut::new(&mut rx2).poll_next(cx2), Poll::Ready(Some(1)));
+    assert!(PinMut::new(&mut rx1).poll_next(cx1).is_pending());
+    tx.unbounded_send(2).unwrap();
+    assert_eq!(counter1.0.load(Ordering::SeqCst), 2);
+    assert_eq!(counter2.0.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn dropping_senders_ends_every_receiver() {
+    let (tx, rx1) = mpmc::channel::<i32>(1);
+    let rx2 = rx1.clone();
+    drop(tx);
+
+    assert_eq!(block_on(rx1.collect::<Vec<_>>()), vec![]);
+    assert_eq!(block_on(rx2.collect::<Vec<_>>()), vec![]);
+}
+
+#[test]
+fn dropping_receivers_fails_send() {
+    let (mut tx, rx1) = mpmc::channel::<i32>(1);
+    let rx2 = rx1.clone();
+
+    drop(rx1);
+    assert!(tx.try_send(1).is_ok());
+
+    drop(rx2);
+    assert!(tx.is_closed());
+    assert!(tx.try_send(2).unwrap_err().is_disconnected());
+}
+
+#[test]
+fn close_stops_sends_and_drains() {
+    let (tx, mut rx1) = mpmc::unbounded::<i32>();
+    let rx2 = rx1.clone();
+
+    tx.unbounded_send(1).unwrap();
+    rx1.close();
+    assert!(tx.unbounded_send(2).unwrap_err().is_disconnected());
+
+    drop(rx1);
+    let v: Vec<_> = block_on(rx2.collect());
+    assert_eq!(v, vec![1]);
+}
+
+#[test]
+fn recv_threads() {
+    const N: i32 = 1000;
+    const RECEIVERS: usize = 4;
+
+    let (mut tx, rx) = mpmc::channel::<i32>(4);
+
+    let threads = (0..RECEIVERS).map(|_| {
+        let rx = rx.clone();
+        thread::spawn(move || block_on(rx.collect::<Vec<_>>()))
+    }).collect::<Vec<_>>();
+    drop(rx);
+
+    for i in 0..N {
+        block_on(tx.send(i)).unwrap();
+    }
+    drop(tx);
+
+    let mut received = threads.into_iter()
+        .flat_map(|t| t.join().unwrap())
+        .collect::<Vec<_>>();
+    received.sort();
+    assert_eq!(received, (0..N).collect::<Vec<_>>());
+}
@@ -524,3 +524,149 @@ fn try_send_recv() {
     rx.try_next().unwrap();
     rx.try_next().unwrap_err(); // should be empty
 }
+
+#[test]
+fn len_and_capacity() {
+    let (mut tx, mut rx) = mpsc::channel(1);
+    assert_eq!(tx.capacity(), 2);
+    assert!(tx.is_empty() && rx.is_empty());
+
+    let mut tx2 = tx.clone();
+    assert_eq!(rx.capacity(), 3);
+    assert_eq!(rx.num_senders(), 2);
+
+    tx.try_send("hello").unwrap();
+    tx.try_send("hello").unwrap();
+    assert_eq!(rx.len(), 2);
+    assert_eq!(rx.num_parked_senders(), 1);
+
+    tx2.try_send("hello").unwrap();
+    assert_eq!(tx.len(), 3);
+    assert_eq!(tx.num_parked_senders(), 2);
+
+    rx.try_next().unwrap();
+    assert_eq!(rx.len(), 2);
+    assert_eq!(rx.num_parked_senders(), 1);
+
+    // Closing the channel doesn't count as a message
+    drop(tx);
+    drop(tx2);
+    assert_eq!(rx.num_senders(), 0);
+    assert_eq!(rx.len(), 2);
+    rx.try_next().unwrap();
+    rx.try_next().unwrap();
+    assert!(rx.is_empty());
+    assert_eq!(rx.num_parked_senders(), 0);
+    assert_eq!(rx.try_next().unwrap(), None);
+}
+
+#[test]
+fn close_unparks_senders() {
+    let (mut tx, mut rx) = mpsc::channel(0);
+    let mut tx2 = tx.clone();
+
+    tx.try_send(1).unwrap();
+    tx2.try_send(2).unwrap();
+    assert_eq!(rx.num_parked_senders(), 2);
+
+    rx.close();
+    assert_eq!(rx.num_parked_senders(), 0);
+    assert!(tx.try_send(3).unwrap_err().is_disconnected());
+    assert_eq!(rx.num_parked_senders(), 0);
+}
+
+#[test]
+fn unbounded_len() {
+    let (tx, mut rx) = mpsc::unbounded();
+    assert!(rx.is_empty());
+
+    tx.unbounded_send(1).unwrap();
+    tx.unbounded_send(2).unwrap();
+    assert_eq!(tx.len(), 2);
+    assert_eq!(rx.num_senders(), 1);
+
+    rx.try_next().unwrap();
+    assert_eq!(rx.len(), 1);
+
+    drop(tx);
+    assert_eq!(rx.len(), 1);
+}
+
+#[test]
+fn weak_sender_does_not_keep_channel_open() {
+    let (mut tx, rx) = mpsc::channel(1);
+    let weak = tx.downgrade();
+    assert_eq!(tx.num_senders(), 1);
+
+    let mut tx2 = weak.upgrade().unwrap();
+    assert_eq!(tx.num_senders(), 2);
+
+    tx.try_send(1).unwrap();
+    tx2.try_send(2).unwrap();
+    drop(tx);
+    drop(tx2);
+
+    // All strong senders are gone, the stream terminates
+    assert!(weak.upgrade().is_none());
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![1, 2]);
+}
+
+#[test]
+fn weak_sender_upgrade_fails_when_closed() {
+    let (tx, mut rx) = mpsc::unbounded::<i32>();
+    let weak = tx.downgrade();
+
+    weak.upgrade().unwrap().unbounded_send(1).unwrap();
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+
+    rx.close();
+    assert!(weak.upgrade().is_none());
+    drop(rx);
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn recv_many() {
+    let (mut tx, mut rx) = mpsc::channel(1);
+    let mut tx2 = tx.clone();
+
+    tx.try_send(1).unwrap();
+    tx.try_send(2).unwrap();
+    tx2.try_send(3).unwrap();
+    assert_eq!(rx.num_parked_senders(), 2);
+
+    let mut buf = Vec::new();
+    assert_eq!(block_on(rx.recv_many(&mut buf, 2)), 2);
+    assert_eq!(buf, vec![1, 2]);
+    assert_eq!(rx.len(), 1);
+    assert_eq!(rx.num_parked_senders(), 0);
+
+    drop(tx);
+    drop(tx2);
+    assert_eq!(block_on(rx.recv_many(&mut buf, 10)), 1);
+    assert_eq!(buf, vec![1, 2, 3]);
+    assert_eq!(block_on(rx.recv_many(&mut buf, 10)), 0);
+}
+
+#[test]
+fn poll_recv_many_pending() {
+    let (tx, mut rx) = mpsc::unbounded::<i32>();
+
+    block_on(poll_fn(move |cx| {
+        let mut buf = Vec::new();
+        assert!(rx.poll_recv_many(cx, &mut buf, 4).is_pending());
+        assert_eq!(rx.poll_recv_many(cx, &mut buf, 0), Poll::Ready(0));
+
+        for i in 0..6 {
+            tx.unbounded_send(i).unwrap();
+        }
+        assert_eq!(rx.poll_recv_many(cx, &mut buf, 4), Poll::Ready(4));
+        assert_eq!(rx.poll_recv_many(cx, &mut buf, 4), Poll::Ready(2));
+        assert_eq!(buf, (0..6).collect::<Vec<_>>());
+
+        tx.close_channel();
+        assert_eq!(rx.poll_recv_many(cx, &mut buf, 4), Poll::Ready(0));
+        Poll::Ready(())
+    }));
+}
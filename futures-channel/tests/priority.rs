@@ -0,0 +1,99 @@
+#![feature(futures_api, arbitrary_self_types, pin)]
+
+use futures::channel::priority;
+use futures::executor::{block_on, block_on_stream};
+use futures::future::poll_fn;
+use futures::stream::{Stream, StreamExt};
+use futures::sink::{Sink, SinkExt};
+use futures::task::Poll;
+use pin_utils::pin_mut;
+use std::thread;
+
+trait AssertSend: Send {}
+impl AssertSend for priority::Sender<u8, i32> {}
+impl AssertSend for priority::Receiver<u8, i32> {}
+
+#[test]
+fn highest_priority_first() {
+    let (mut tx, rx) = priority::channel::<u8, &str>(16);
+
+    tx.try_send(1, "low").unwrap();
+    tx.try_send(3, "high").unwrap();
+    tx.try_send(2, "mid").unwrap();
+    drop(tx);
+
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec!["high", "mid", "low"]);
+}
+
+#[test]
+fn fifo_within_priority() {
+    let (mut tx, rx) = priority::channel::<u8, i32>(16);
+
+    for i in 0..4 {
+        block_on(tx.send((0, i))).unwrap();
+    }
+    block_on(tx.send((1, 10))).unwrap();
+    for i in 4..8 {
+        block_on(tx.send((0, i))).unwrap();
+    }
+    drop(tx);
+
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![10, 0, 1, 2, 3, 4, 5, 6, 7]);
+}
+
+#[test]
+fn send_recv_no_buffer() {
+    // Run on a task context
+    block_on(poll_fn(move |cx| {
+        let (tx, rx) = priority::channel::<u8, i32>(0);
+        pin_mut!(tx, rx);
+
+        assert!(tx.reborrow().poll_ready(cx).is_ready());
+
+        // Send first message
+        assert!(tx.reborrow().start_send((0, 1)).is_ok());
+        assert!(tx.reborrow().poll_ready(cx).is_pending());
+
+        // poll_ready said Pending, so no room in buffer, therefore new sends
+        // should get rejected with is_full.
+        assert!(tx.reborrow().start_send((0, 2)).unwrap_err().is_full());
+
+        // Take the value
+        assert_eq!(rx.reborrow().poll_next(cx), Poll::Ready(Some(1)));
+        assert!(tx.reborrow().poll_ready(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn receiver_drop_fails_send() {
+    let (mut tx, rx) = priority::channel::<u8, i32>(1);
+    drop(rx);
+
+    assert!(tx.is_closed());
+    let err = tx.try_send(0, 1).unwrap_err();
+    assert!(err.is_disconnected());
+    assert_eq!(err.into_inner(), (0, 1));
+}
+
+#[test]
+fn send_recv_threads() {
+    const N: i32 = 1000;
+
+    let (mut tx, rx) = priority::channel::<bool, i32>(4);
+
+    let t = thread::spawn(move || {
+        for i in 0..N {
+            block_on(tx.send((i % 10 == 0, i))).unwrap();
+        }
+    });
+
+    let mut received = block_on_stream(rx).collect::<Vec<_>>();
+    t.join().unwrap();
+
+    received.sort();
+    assert_eq!(received, (0..N).collect::<Vec<_>>());
+}
@@ -0,0 +1,102 @@
+#![feature(futures_api, arbitrary_self_types, pin)]
+
+use futures::channel::rendezvous;
+use futures::executor::block_on;
+use futures::future::poll_fn;
+use futures::stream::{Stream, StreamExt};
+use futures::sink::{Sink, SinkExt};
+use futures::task::Poll;
+use pin_utils::pin_mut;
+use std::thread;
+
+trait AssertSend: Send {}
+impl AssertSend for rendezvous::Sender<i32> {}
+impl AssertSend for rendezvous::Receiver<i32> {}
+
+#[test]
+fn send_recv() {
+    let (mut tx, rx) = rendezvous::channel::<i32>();
+
+    let t = thread::spawn(move || block_on(rx.collect::<Vec<_>>()));
+
+    block_on(tx.send(1)).unwrap();
+    block_on(tx.send(2)).unwrap();
+    drop(tx);
+
+    assert_eq!(t.join().unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn flush_waits_for_receiver() {
+    block_on(poll_fn(move |cx| {
+        let (tx, rx) = rendezvous::channel::<i32>();
+        pin_mut!(tx, rx);
+
+        assert!(tx.reborrow().poll_ready(cx).is_ready());
+        assert!(tx.reborrow().start_send(1).is_ok());
+
+        // Nothing is buffered: the message is only delivered once received
+        assert!(tx.reborrow().poll_flush(cx).is_pending());
+        assert!(tx.reborrow().poll_ready(cx).is_pending());
+        assert!(tx.reborrow().start_send(2).unwrap_err().is_full());
+
+        assert_eq!(rx.reborrow().poll_next(cx), Poll::Ready(Some(1)));
+        assert_eq!(tx.reborrow().poll_flush(cx), Poll::Ready(Ok(())));
+        assert!(tx.reborrow().poll_ready(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn other_senders_wait_for_handoff() {
+    block_on(poll_fn(move |cx| {
+        let (mut tx1, mut rx) = rendezvous::channel::<i32>();
+        let mut tx2 = tx1.clone();
+
+        tx1.start_send(1).unwrap();
+        assert!(tx2.poll_ready(cx).is_pending());
+        assert!(tx2.poll_flush(cx).is_ready());
+
+        assert_eq!(rx.poll_next_unpin(cx), Poll::Ready(Some(1)));
+        assert!(tx2.poll_ready(cx).is_ready());
+        tx2.start_send(2).unwrap();
+        assert!(tx1.poll_flush(cx).is_ready());
+        assert!(tx2.poll_flush(cx).is_pending());
+
+        assert_eq!(rx.poll_next_unpin(cx), Poll::Ready(Some(2)));
+        assert!(tx2.poll_flush(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn receiver_drop_fails_pending_send() {
+    block_on(poll_fn(move |cx| {
+        let (mut tx, rx) = rendezvous::channel::<i32>();
+
+        tx.start_send(1).unwrap();
+        assert!(tx.poll_flush(cx).is_pending());
+
+        drop(rx);
+        assert!(tx.is_closed());
+        match tx.poll_flush(cx) {
+            Poll::Ready(Err(e)) => assert!(e.is_disconnected()),
+            _ => panic!("flush should fail once the receiver is gone"),
+        }
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn dropping_senders_ends_stream() {
+    let (mut tx, rx) = rendezvous::channel::<i32>();
+
+    tx.try_send(1).unwrap();
+    drop(tx);
+
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![1]);
+}
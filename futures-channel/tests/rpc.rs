@@ -0,0 +1,155 @@
+#![feature(futures_api, arbitrary_self_types, pin)]
+
+use futures::channel::oneshot::Canceled;
+use futures::channel::rpc;
+use futures::executor::{block_on, block_on_stream};
+use futures::future::{self, poll_fn, FutureExt};
+use futures::stream::StreamExt;
+use futures::task::Poll;
+use std::thread;
+
+trait AssertSend: Send {}
+impl AssertSend for rpc::Client<i32, i32> {}
+impl AssertSend for rpc::Server<i32, i32> {}
+
+#[test]
+fn call_respond() {
+    let (client, server) = rpc::channel::<i32, i32>(1);
+
+    let t = thread::spawn(move || {
+        for (req, responder) in block_on_stream(server) {
+            responder.respond(req * 2).unwrap();
+        }
+    });
+
+    assert_eq!(block_on(client.call(1)), Ok(2));
+    assert_eq!(block_on(client.call(21)), Ok(42));
+    drop(client);
+
+    t.join().unwrap();
+}
+
+#[test]
+fn server_drop_cancels_calls() {
+    let (client, server) = rpc::channel::<i32, i32>(1);
+
+    let mut server = block_on_stream(server);
+    let t = thread::spawn(move || {
+        let (req, responder) = server.next().unwrap();
+        assert_eq!(req, 1);
+        drop(responder);
+    });
+
+    assert_eq!(block_on(client.call(1)), Err(Canceled));
+    t.join().unwrap();
+
+    assert!(client.is_closed());
+    assert_eq!(block_on(client.call(2)), Err(Canceled));
+}
+
+#[test]
+fn dropped_call_is_canceled() {
+    let (client, mut server) = rpc::channel::<i32, i32>(1);
+
+    block_on(poll_fn(move |cx| {
+        {
+            let mut call = client.call(1);
+            assert!(call.poll_unpin(cx).is_pending());
+        }
+
+        let (req, mut responder) = match server.poll_next_unpin(cx) {
+            Poll::Ready(Some(item)) => item,
+            _ => panic!("request should have been sent"),
+        };
+        assert_eq!(req, 1);
+        assert!(responder.is_canceled());
+        assert_eq!(responder.poll_cancel(cx), Poll::Ready(()));
+        assert_eq!(responder.respond(2), Err(2));
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn concurrent_clients() {
+    let (client, server) = rpc::channel::<i32, i32>(0);
+
+    let threads = (0..4).map(|i| {
+        let client = client.clone();
+        thread::spawn(move || {
+            assert_eq!(block_on(client.call(i)), Ok(i + 1));
+        })
+    }).collect::<Vec<_>>();
+    drop(client);
+
+    let served = block_on(server.fold(0, |n, (req, responder)| {
+        responder.respond(req + 1).unwrap();
+        future::ready(n + 1)
+    }));
+    assert_eq!(served, 4);
+
+    for t in threads {
+        t.join().unwrap();
+    }
+}
+
+#[test]
+fn concurrent_calls_on_one_client() {
+    let (client, server) = rpc::channel::<i32, i32>(0);
+
+    let t = thread::spawn(move || {
+        for (req, responder) in block_on_stream(server) {
+            responder.respond(req * 2).unwrap();
+        }
+    });
+
+    let calls = client.call(1).join(client.call(2));
+    assert_eq!(block_on(calls), (Ok(2), Ok(4)));
+    drop(client);
+
+    t.join().unwrap();
+}
+
+#[test]
+fn calls_wait_for_server() {
+    let (client, mut server) = rpc::channel::<i32, i32>(1);
+
+    block_on(poll_fn(move |cx| {
+        // The channel holds `buffer + 1` requests, the last call has to wait
+        let mut calls = (0..3).map(|i| client.call(i)).collect::<Vec<_>>();
+        for call in &mut calls {
+            assert!(call.poll_unpin(cx).is_pending());
+        }
+
+        let mut responders = Vec::new();
+        for i in 0..2 {
+            match server.poll_next_unpin(cx) {
+                Poll::Ready(Some((req, responder))) => {
+                    assert_eq!(req, i);
+                    responders.push(responder);
+                }
+                _ => panic!("request should have been sent"),
+            }
+        }
+        assert!(server.poll_next_unpin(cx).is_pending());
+
+        // Receiving the requests made room for the last one
+        assert!(calls[2].poll_unpin(cx).is_pending());
+        match server.poll_next_unpin(cx) {
+            Poll::Ready(Some((req, responder))) => {
+                assert_eq!(req, 2);
+                responders.push(responder);
+            }
+            _ => panic!("request should have been sent"),
+        }
+
+        for (responder, i) in responders.into_iter().zip(0..) {
+            responder.respond(i * 2).unwrap();
+        }
+        for (call, i) in calls.iter_mut().zip(0..) {
+            assert_eq!(call.poll_unpin(cx), Poll::Ready(Ok(i * 2)));
+        }
+
+        Poll::Ready(())
+    }));
+}
@@ -0,0 +1,110 @@
+#![feature(futures_api, arbitrary_self_types, pin)]
+
+use futures::channel::watch;
+use futures::executor::{block_on, block_on_stream};
+use futures::future::poll_fn;
+use futures::stream::StreamExt;
+use futures::task::Poll;
+use std::thread;
+
+trait AssertSend: Send {}
+impl AssertSend for watch::Sender<i32> {}
+impl AssertSend for watch::Receiver<i32> {}
+
+#[test]
+fn borrow_initial_value() {
+    let (tx, rx) = watch::channel("one");
+    assert_eq!(*rx.borrow(), "one");
+
+    tx.broadcast("two").unwrap();
+    assert_eq!(*rx.borrow(), "two");
+}
+
+#[test]
+fn stream_skips_intermediate_values() {
+    let (tx, rx) = watch::channel(0);
+    let mut rx = block_on_stream(rx);
+
+    tx.broadcast(1).unwrap();
+    tx.broadcast(2).unwrap();
+    tx.broadcast(3).unwrap();
+    assert_eq!(rx.next(), Some(3));
+
+    tx.broadcast(4).unwrap();
+    assert_eq!(rx.next(), Some(4));
+
+    drop(tx);
+    assert_eq!(rx.next(), None);
+}
+
+#[test]
+fn pending_until_changed() {
+    let (tx, mut rx) = watch::channel(0);
+
+    block_on(poll_fn(move |cx| {
+        assert!(rx.poll_next_unpin(cx).is_pending());
+        tx.broadcast(1).unwrap();
+        assert_eq!(rx.poll_next_unpin(cx), Poll::Ready(Some(1)));
+        assert!(rx.poll_next_unpin(cx).is_pending());
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn cloned_receivers_track_changes_independently() {
+    let (tx, rx1) = watch::channel(0);
+    let mut rx1 = block_on_stream(rx1);
+
+    tx.broadcast(1).unwrap();
+    assert_eq!(rx1.next(), Some(1));
+
+    let rx1 = rx1.into_inner();
+    let rx2 = rx1.clone();
+    tx.broadcast(2).unwrap();
+    drop(tx);
+
+    assert_eq!(block_on(rx1.collect::<Vec<_>>()), vec![2]);
+    assert_eq!(block_on(rx2.collect::<Vec<_>>()), vec![2]);
+}
+
+#[test]
+fn sender_drop_is_observed() {
+    let (tx, rx) = watch::channel(0);
+    assert!(!rx.is_closed());
+
+    drop(tx);
+    assert!(rx.is_closed());
+    assert_eq!(*rx.borrow(), 0);
+    assert_eq!(block_on(rx.collect::<Vec<_>>()), vec![]);
+}
+
+#[test]
+fn broadcast_without_receivers_fails() {
+    let (tx, rx) = watch::channel(0);
+    assert!(!tx.is_closed());
+
+    drop(rx);
+    assert!(tx.is_closed());
+    assert_eq!(tx.broadcast(1).unwrap_err().into_inner(), 1);
+}
+
+#[test]
+fn watch_threads() {
+    let (tx, rx) = watch::channel(0);
+
+    let t = thread::spawn(move || {
+        let mut last = 0;
+        for value in block_on_stream(rx) {
+            assert!(value > last);
+            last = value;
+        }
+        last
+    });
+
+    for i in 1..=100 {
+        tx.broadcast(i).unwrap();
+    }
+    drop(tx);
+
+    assert_eq!(t.join().unwrap(), 100);
+}
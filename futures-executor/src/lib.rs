@@ -18,11 +18,11 @@ macro_rules! if_std {
 
 if_std! {
     mod local_pool;
-    pub use crate::local_pool::{block_on, block_on_stream, BlockingStream, LocalPool, LocalSpawn};
+    pub use crate::local_pool::{block_on, block_on_stream, BlockingStream, LocalPool, LocalSpawn, RemoteSpawn};
 
     mod unpark_mutex;
     mod thread_pool;
-    pub use crate::thread_pool::{ThreadPool, ThreadPoolBuilder};
+    pub use crate::thread_pool::{ShutdownOnIdle, ThreadPool, ThreadPoolBuilder};
 
     mod enter;
     pub use crate::enter::{enter, Enter, EnterError};
@@ -7,6 +7,7 @@ use futures_core::task::{
 };
 use futures_util::stream::FuturesUnordered;
 use futures_util::stream::StreamExt;
+use futures_util::task::AtomicWaker;
 use lazy_static::lazy_static;
 use pin_utils::pin_mut;
 use std::cell::{RefCell};
@@ -14,7 +15,7 @@ use std::marker::Unpin;
 use std::ops::{Deref, DerefMut};
 use std::prelude::v1::*;
 use std::rc::{Rc, Weak};
-use std::sync::Arc;
+use std::sync::{self, Arc, Mutex};
 use std::thread::{self, Thread};
 
 /// A single-threaded task pool for polling futures to completion.
@@ -28,10 +29,21 @@ use std::thread::{self, Thread};
 /// [`spawner()`](LocalPool::spawner) method. Because the executor is
 /// single-threaded, it supports a special form of task spawning for non-`Send`
 /// futures, via [`spawn_local_obj`](LocalSpawn::spawn_local_obj).
+///
+/// Besides the blocking [`run`](LocalPool::run) and
+/// [`run_until`](LocalPool::run_until) methods, the pool can be driven step
+/// by step without blocking, e.g. from tests or from the loop of another
+/// event system, with [`run_until_stalled`](LocalPool::run_until_stalled),
+/// [`try_run_one`](LocalPool::try_run_one) and
+/// [`poll_once`](LocalPool::poll_once).
+///
+/// Other threads can spawn `Send` futures onto the pool through the handle
+/// returned by [`remote_spawner()`](LocalPool::remote_spawner).
 #[derive(Debug)]
 pub struct LocalPool {
     pool: FuturesUnordered<LocalFutureObj<'static, ()>>,
     incoming: Rc<Incoming>,
+    remote: Arc<RemoteIncoming>,
 }
 
 /// A handle to a [`LocalPool`](LocalPool) that implements
@@ -43,6 +55,23 @@ pub struct LocalSpawn {
 
 type Incoming = RefCell<Vec<LocalFutureObj<'static, ()>>>;
 
+/// A handle to a [`LocalPool`](LocalPool) that implements
+/// [`Spawn`](futures_core::task::Spawn), and can be sent to other threads to
+/// spawn `Send` futures onto the pool from there.
+#[derive(Clone, Debug)]
+pub struct RemoteSpawn {
+    remote: sync::Weak<RemoteIncoming>,
+}
+
+// Tasks spawned from any thread through a `RemoteSpawn`
+#[derive(Debug)]
+struct RemoteIncoming {
+    tasks: Mutex<Vec<FutureObj<'static, ()>>>,
+    // The task polling the pool, woken up when a task is spawned. While the
+    // pool is run, this unparks its thread through its `ThreadNotify`.
+    waker: AtomicWaker,
+}
+
 pub(crate) struct ThreadNotify {
     thread: Thread
 }
@@ -59,6 +88,20 @@ impl Wake for ThreadNotify {
     }
 }
 
+// Set up a waker unparking the current thread and invoke `f` once with it,
+// without blocking.
+fn run_step<T, F: FnOnce(&LocalWaker) -> T>(f: F) -> T {
+    let _enter = enter()
+        .expect("cannot execute `LocalPool` executor from within \
+                 another executor");
+
+    CURRENT_THREAD_NOTIFY.with(|thread_notify| {
+        let local_waker =
+          task::local_waker_from_nonlocal(thread_notify.clone());
+        f(&local_waker)
+    })
+}
+
 // Set up and run a basic single-threaded spawner loop, invoking `f` on each
 // turn.
 fn run_executor<T, F: FnMut(&LocalWaker) -> Poll<T>>(mut f: F) -> T {
@@ -84,6 +127,10 @@ impl LocalPool {
         LocalPool {
             pool: FuturesUnordered::new(),
             incoming: Default::default(),
+            remote: Arc::new(RemoteIncoming {
+                tasks: Mutex::new(Vec::new()),
+                waker: AtomicWaker::new(),
+            }),
         }
     }
 
@@ -94,6 +141,37 @@ impl LocalPool {
         }
     }
 
+    /// Get a clonable handle to the pool as a [`Spawn`], which can be sent to
+    /// other threads.
+    ///
+    /// Unlike [`spawner()`](LocalPool::spawner), the handle only spawns
+    /// `Send` futures, but it can do so from any thread. Spawning a task
+    /// wakes the pool up if it is blocked in [`run`](LocalPool::run) or
+    /// [`run_until`](LocalPool::run_until).
+    ///
+    /// ```
+    /// #![feature(async_await, futures_api)]
+    /// use futures::executor::LocalPool;
+    /// use futures::task::SpawnExt;
+    /// use std::thread;
+    ///
+    /// let mut pool = LocalPool::new();
+    /// let mut spawn = pool.spawner();
+    /// let mut remote = pool.remote_spawner();
+    ///
+    /// let handle = thread::spawn(move || {
+    ///     remote.spawn(async { /* ... */ }).unwrap();
+    /// });
+    /// handle.join().unwrap();
+    ///
+    /// pool.run(&mut spawn);
+    /// ```
+    pub fn remote_spawner(&self) -> RemoteSpawn {
+        RemoteSpawn {
+            remote: Arc::downgrade(&self.remote)
+        }
+    }
+
     /// Run all tasks in the pool to completion.
     ///
     /// The given spawner, `spawn`, is used as the default spawner for any
@@ -165,6 +243,92 @@ impl LocalPool {
         })
     }
 
+    /// Runs all the tasks in the pool until none of them can make progress
+    /// anymore, without blocking the calling thread.
+    ///
+    /// The given spawner, `spawn`, is used as the default spawner for any
+    /// *newly*-spawned tasks, as with [`run`](LocalPool::run). Returns the
+    /// number of tasks left in the pool, which wait to be woken up.
+    ///
+    /// ```
+    /// #![feature(async_await, futures_api)]
+    /// use futures::executor::LocalPool;
+    /// use futures::task::SpawnExt;
+    ///
+    /// let mut pool = LocalPool::new();
+    /// let mut spawn = pool.spawner();
+    ///
+    /// spawn.spawn(async { /* ... */ }).unwrap();
+    /// assert_eq!(pool.run_until_stalled(&mut spawn), 0);
+    /// ```
+    pub fn run_until_stalled<Sp>(&mut self, spawn: &mut Sp) -> usize
+        where Sp: Spawn + Sized
+    {
+        run_step(|local_waker| {
+            let _ = self.poll_pool(local_waker, spawn);
+        });
+        self.len()
+    }
+
+    /// Runs the tasks in the pool until one of them completes or none of them
+    /// can make progress anymore, without blocking the calling thread.
+    ///
+    /// The given spawner, `spawn`, is used as the default spawner for any
+    /// *newly*-spawned tasks, as with [`run`](LocalPool::run). Returns the
+    /// number of tasks left in the pool.
+    pub fn try_run_one<Sp>(&mut self, spawn: &mut Sp) -> usize
+        where Sp: Spawn + Sized
+    {
+        run_step(|local_waker| {
+            let mut pool_cx = Context::new(local_waker, spawn);
+
+            loop {
+                // a task completed
+                if let Poll::Ready(Some(())) = self.poll_pool_once(&mut pool_cx) {
+                    return;
+                }
+
+                // no further progress can be made unless we queued up some
+                // new tasks
+                if self.incoming.borrow().is_empty() {
+                    return;
+                }
+            }
+        });
+        self.len()
+    }
+
+    /// Polls the tasks in the pool once, completing at most one of them,
+    /// and returns the number of tasks left in the pool.
+    ///
+    /// Tasks are polled with the waker and spawner of `cx`: the waker is
+    /// woken up whenever a task can make progress, so that the pool can be
+    /// polled again, and the spawner is the default spawner for any
+    /// *newly*-spawned tasks. This lets another event loop, or another
+    /// executor, drive the pool without blocking.
+    pub fn poll_once(&mut self, cx: &mut Context) -> usize {
+        // ask to be polled again if further progress may be made, as the
+        // remaining tasks won't wake us up: a task completed and others may
+        // be ready, or we queued up some new tasks
+        let may_progress = match self.poll_pool_once(cx) {
+            Poll::Ready(Some(())) => true,
+            _ => !self.incoming.borrow().is_empty(),
+        };
+
+        let len = self.len();
+        if may_progress && len > 0 {
+            cx.waker().wake();
+        }
+        len
+    }
+
+    // Number of tasks in the pool, including the newly-spawned ones
+    fn len(&self) -> usize {
+        self.pool.len()
+            + self.incoming.borrow().len()
+            + self.remote.tasks.lock().unwrap().len()
+    }
+
     // Make maximal progress on the entire pool of spawned task, returning `Ready`
     // if the pool is empty and `Pending` if no further progress can be made.
     fn poll_pool<Sp>(&mut self, local_waker: &LocalWaker, spawn: &mut Sp)
@@ -175,15 +339,8 @@ impl LocalPool {
         let mut pool_cx = Context::new(local_waker, spawn);
 
         loop {
-            // empty the incoming queue of newly-spawned tasks
-            {
-                let mut incoming = self.incoming.borrow_mut();
-                for task in incoming.drain(..) {
-                    self.pool.push(task)
-                }
-            }
+            let ret = self.poll_pool_once(&mut pool_cx);
 
-            let ret = self.pool.poll_next_unpin(&mut pool_cx);
             // we queued up some new tasks; add them and poll again
             if !self.incoming.borrow().is_empty() {
                 continue;
@@ -197,6 +354,30 @@ impl LocalPool {
             }
         }
     }
+
+    // Move the newly-spawned tasks into the pool and poll it once, completing
+    // at most one task.
+    fn poll_pool_once(&mut self, cx: &mut Context) -> Poll<Option<()>> {
+        // empty the incoming queue of newly-spawned tasks
+        {
+            let mut incoming = self.incoming.borrow_mut();
+            for task in incoming.drain(..) {
+                self.pool.push(task)
+            }
+        }
+
+        // same for the tasks spawned from other threads, registering to be
+        // woken up when more are spawned
+        self.remote.waker.register(cx.waker());
+        {
+            let mut tasks = self.remote.tasks.lock().unwrap();
+            for task in tasks.drain(..) {
+                self.pool.push(task.into())
+            }
+        }
+
+        self.pool.poll_next_unpin(cx)
+    }
 }
 
 impl Default for LocalPool {
@@ -286,6 +467,29 @@ impl Spawn for LocalSpawn {
     }
 }
 
+impl Spawn for RemoteSpawn {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, ()>,
+    ) -> Result<(), SpawnObjError> {
+        if let Some(remote) = self.remote.upgrade() {
+            remote.tasks.lock().unwrap().push(future);
+            remote.waker.wake();
+            Ok(())
+        } else {
+            Err(SpawnObjError{ future, kind: SpawnErrorKind::shutdown() })
+        }
+    }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        if self.remote.upgrade().is_some() {
+            Ok(())
+        } else {
+            Err(SpawnErrorKind::shutdown())
+        }
+    }
+}
+
 impl LocalSpawn {
     /// Spawn a non-`Send` future onto the associated [`LocalPool`](LocalPool).
     pub fn spawn_local_obj(
@@ -1,15 +1,21 @@
 use crate::enter;
 use crate::unpark_mutex::UnparkMutex;
+use crossbeam_deque::{Injector, Stealer, Worker};
 use futures_core::future::{Future, FutureObj};
-use futures_core::task::{self, Poll, Wake, Spawn, SpawnObjError};
+use futures_core::task::{self, Poll, Wake, Spawn, SpawnErrorKind, SpawnObjError};
 use futures_util::future::FutureExt;
-use futures_util::task::local_waker_ref_from_nonlocal;
+use futures_util::task::{local_waker_ref_from_nonlocal, WakerSet};
 use num_cpus;
+use std::any::Any;
+use std::cell::RefCell;
 use std::io;
+use std::iter;
+use std::marker::Unpin;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::PinMut;
 use std::prelude::v1::*;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{self, AtomicBool, AtomicUsize, Ordering};
 use std::thread;
 use std::fmt;
 
@@ -19,6 +25,21 @@ use std::fmt;
 /// The thread pool multiplexes any number of tasks onto a fixed number of
 /// worker threads.
 ///
+/// Each worker thread has its own queue of tasks: tasks spawned or woken up
+/// from a worker thread are queued on that worker, while tasks spawned or
+/// woken up from elsewhere go through a queue shared by the whole pool.
+/// Workers running out of tasks steal some from the other workers.
+///
+/// A task whose future panics is dropped without affecting the worker thread
+/// running it, nor the other tasks. The panic can be observed with a
+/// [`panic_handler`](ThreadPoolBuilder::panic_handler), or through the
+/// `JoinHandle` of a task spawned with `spawn_with_handle`, but not both.
+///
+/// The worker threads stop once the pool is [shut down](ThreadPool::shutdown)
+/// and all its tasks have completed, or once all the handles to the pool are
+/// dropped. Tasks hold a handle to the pool they run on, so a pool isn't
+/// dropped before its tasks.
+///
 /// This type is a clonable handle to the threadpool itself.
 /// Cloning it will only create a new reference, not a new threadpool.
 pub struct ThreadPool {
@@ -32,18 +53,52 @@ pub struct ThreadPoolBuilder {
     name_prefix: Option<String>,
     after_start: Option<Arc<dyn Fn(usize) + Send + Sync>>,
     before_stop: Option<Arc<dyn Fn(usize) + Send + Sync>>,
+    panic_handler: Option<Arc<dyn Fn(Box<dyn Any + Send>) + Send + Sync>>,
 }
 
 trait AssertSendSync: Send + Sync {}
 impl AssertSendSync for ThreadPool {}
 
 struct PoolState {
-    tx: Mutex<mpsc::Sender<Message>>,
-    rx: Mutex<mpsc::Receiver<Message>>,
+    // Tasks spawned or woken up from outside of the worker threads
+    injector: Injector<Task>,
+    // Handles to steal from the queue of each worker thread, which holds the
+    // tasks spawned or woken up on it
+    stealers: Vec<Stealer<Task>>,
+    parkers: Vec<Parker>,
+    // Indices of the workers sleeping for lack of tasks, only locked to put
+    // a worker to sleep or to wake one up
+    sleepers: Mutex<Vec<usize>>,
+    num_sleeping: AtomicUsize,
+    // Number of workers woken up to look for tasks which haven't found one
+    // yet. While one is looking, queueing a task doesn't wake anyone up.
+    num_searching: AtomicUsize,
+    closed: AtomicBool,
+    // Whether new tasks are refused
+    is_shutdown: AtomicBool,
+    // Whether to shut down once there is no task left
+    shutdown_on_idle: AtomicBool,
+    // Number of tasks spawned which haven't completed yet
+    num_tasks: AtomicUsize,
+    // Tasks waiting for `num_tasks` to reach zero
+    idle_wakers: WakerSet,
+    threads: Mutex<Vec<thread::JoinHandle<()>>>,
+    panic_handler: Option<Arc<dyn Fn(Box<dyn Any + Send>) + Send + Sync>>,
     cnt: AtomicUsize,
     size: usize,
 }
 
+// Puts a worker thread to sleep until it is woken up
+struct Parker {
+    notified: Mutex<bool>,
+    cvar: Condvar,
+}
+
+thread_local! {
+    // The pool and the queue of the worker running on this thread, if any
+    static WORKER: RefCell<Option<(*const PoolState, Worker<Task>)>> = RefCell::new(None);
+}
+
 impl fmt::Debug for ThreadPool {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("ThreadPool")
@@ -61,11 +116,6 @@ impl fmt::Debug for ThreadPoolBuilder {
     }
 }
 
-enum Message {
-    Run(Task),
-    Close,
-}
-
 impl ThreadPool {
     /// Creates a new thread pool with the default configuration.
     ///
@@ -97,6 +147,58 @@ impl ThreadPool {
     pub fn run<F: Future>(&mut self, f: F) -> F::Output {
         crate::LocalPool::new().run_until(f, self)
     }
+
+    /// Stops accepting new tasks.
+    ///
+    /// Spawning on any handle to the pool fails with a `shutdown` error from
+    /// then on, including from the tasks already running on it. These tasks
+    /// still run to completion, after which the worker threads stop.
+    pub fn shutdown(&self) {
+        self.state.shutdown();
+    }
+
+    /// Shuts the pool down once all the tasks spawned on it have completed,
+    /// returning a future which resolves at that point.
+    ///
+    /// Unlike with [`shutdown`](ThreadPool::shutdown), new tasks can still be
+    /// spawned until then, which keeps the pool running.
+    pub fn shutdown_on_idle(&self) -> ShutdownOnIdle {
+        self.state.shutdown_on_idle.store(true, Ordering::SeqCst);
+        if self.state.num_tasks.load(Ordering::SeqCst) == 0 {
+            self.state.shutdown();
+        }
+
+        ShutdownOnIdle {
+            state: self.state.clone(),
+            wait_key: None,
+        }
+    }
+
+    /// Blocks the current thread until all the worker threads of the pool
+    /// have stopped, after running their
+    /// [`before_stop`](ThreadPoolBuilder::before_stop) hook.
+    ///
+    /// This only returns once the pool is shut down and its tasks have
+    /// completed, or once all the other handles to the pool are dropped.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if called from one of the worker threads of the
+    /// pool, which would wait for itself.
+    pub fn join(self) {
+        assert!(!self.state.is_worker_thread(),
+                "ThreadPool::join called from a worker thread of the pool");
+
+        let state = self.state.clone();
+        drop(self);
+
+        // Concurrent calls wait on the lock for the threads to be joined
+        let mut threads = state.threads.lock().unwrap();
+        for thread in threads.drain(..) {
+            // A panic in a hook has already been reported by the panic hook
+            drop(thread.join());
+        }
+    }
 }
 
 impl Spawn for ThreadPool {
@@ -104,6 +206,14 @@ impl Spawn for ThreadPool {
         &mut self,
         future: FutureObj<'static, ()>,
     ) -> Result<(), SpawnObjError> {
+        // Count the task before checking for shutdown, so that either the
+        // task is refused or the pool waits for it.
+        self.state.num_tasks.fetch_add(1, Ordering::SeqCst);
+        if self.state.is_shutdown.load(Ordering::SeqCst) {
+            self.state.complete_task();
+            return Err(SpawnObjError { future, kind: SpawnErrorKind::shutdown() });
+        }
+
         let task = Task {
             future,
             wake_handle: Arc::new(WakeHandle {
@@ -112,34 +222,257 @@ impl Spawn for ThreadPool {
             }),
             exec: self.clone(),
         };
-        self.state.send(Message::Run(task));
+        self.state.schedule(task);
         Ok(())
     }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        if self.state.is_shutdown.load(Ordering::SeqCst) {
+            Err(SpawnErrorKind::shutdown())
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl PoolState {
-    fn send(&self, msg: Message) {
-        self.tx.lock().unwrap().send(msg).unwrap();
+    fn schedule(&self, task: Task) {
+        // Queue the task on the current worker if it belongs to this pool
+        let task = WORKER.with(|worker| match &*worker.borrow() {
+            Some((pool, queue)) if *pool == self as *const PoolState => {
+                queue.push(task);
+                None
+            }
+            _ => Some(task),
+        });
+        if let Some(task) = task {
+            self.injector.push(task);
+        }
+
+        self.notify_idle();
+    }
+
+    // Returns `true` if the current thread is a worker of this pool.
+    fn is_worker_thread(&self) -> bool {
+        WORKER.with(|worker| match &*worker.borrow() {
+            Some((pool, _)) => *pool == self as *const PoolState,
+            None => false,
+        })
+    }
+
+    // Wakes up a sleeping worker to look for the task just queued, unless a
+    // worker is already looking for tasks.
+    //
+    // The task was queued before checking for sleeping workers, while a
+    // worker going to sleep, or done looking for tasks, announces it before
+    // checking the queues one last time, so that either the task is found or
+    // a worker is woken up.
+    fn notify_idle(&self) {
+        loop {
+            atomic::fence(Ordering::SeqCst);
+            if self.num_searching.load(Ordering::SeqCst) != 0
+                || self.num_sleeping.load(Ordering::SeqCst) == 0
+            {
+                return;
+            }
+
+            // Only one waker goes on to wake a worker up, the others rely on
+            // that worker to find their task.
+            if self.num_searching.compare_and_swap(0, 1, Ordering::SeqCst) != 0 {
+                return;
+            }
+
+            let sleeper = {
+                let mut sleepers = self.sleepers.lock().unwrap();
+                let sleeper = sleepers.pop();
+                if sleeper.is_some() {
+                    self.num_sleeping.fetch_sub(1, Ordering::SeqCst);
+                }
+                sleeper
+            };
+            if let Some(idx) = sleeper {
+                // The worker is counted as searching on its behalf
+                self.parkers[idx].unpark();
+                return;
+            }
+
+            // The workers woke up on their own in the meantime. Tasks queued
+            // by wakers which relied on this one would be missed otherwise.
+            self.num_searching.fetch_sub(1, Ordering::SeqCst);
+            atomic::fence(Ordering::SeqCst);
+            if !self.has_tasks() {
+                return;
+            }
+        }
+    }
+
+    fn handle_panic(&self, payload: Box<dyn Any + Send>) {
+        if let Some(panic_handler) = &self.panic_handler {
+            panic_handler(payload);
+        }
+    }
+
+    fn complete_task(&self) {
+        if self.num_tasks.fetch_sub(1, Ordering::SeqCst) == 1 {
+            if self.shutdown_on_idle.load(Ordering::SeqCst) {
+                self.shutdown();
+            }
+            self.idle_wakers.wake_all();
+            if self.is_shutdown.load(Ordering::SeqCst) {
+                self.close();
+            }
+        }
+    }
+
+    fn shutdown(&self) {
+        self.is_shutdown.store(true, Ordering::SeqCst);
+        // Stop the workers unless there are tasks left, in which case the
+        // last one to complete does it.
+        if self.num_tasks.load(Ordering::SeqCst) == 0 {
+            self.close();
+        }
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+
+        let sleepers = {
+            let mut sleepers = self.sleepers.lock().unwrap();
+            self.num_sleeping.fetch_sub(sleepers.len(), Ordering::SeqCst);
+            sleepers.drain(..).collect::<Vec<_>>()
+        };
+        for idx in sleepers {
+            self.parkers[idx].unpark();
+        }
     }
 
     fn work(&self,
             idx: usize,
+            queue: Worker<Task>,
             after_start: Option<Arc<dyn Fn(usize) + Send + Sync>>,
             before_stop: Option<Arc<dyn Fn(usize) + Send + Sync>>) {
         let _scope = enter().unwrap();
+        WORKER.with(|worker| *worker.borrow_mut() = Some((self as *const PoolState, queue)));
         if let Some(after_start) = after_start {
             after_start(idx);
         }
-        loop {
-            let msg = self.rx.lock().unwrap().recv().unwrap();
-            match msg {
-                Message::Run(task) => task.run(),
-                Message::Close => break,
-            }
+        while let Some(task) = WORKER.with(|worker| match &*worker.borrow() {
+            Some((_, queue)) => self.next_task(queue, idx),
+            None => None,
+        }) {
+            task.run();
         }
         if let Some(before_stop) = before_stop {
             before_stop(idx);
         }
+        // Drop the queue outside of the thread local
+        drop(WORKER.with(|worker| worker.borrow_mut().take()));
+    }
+
+    // Waits for a task for the worker `idx` to run, returning `None` once the
+    // pool is closed.
+    fn next_task(&self, queue: &Worker<Task>, idx: usize) -> Option<Task> {
+        // Whether this worker was woken up to look for tasks, and is counted
+        // in `num_searching`
+        let mut searching = false;
+
+        loop {
+            if let Some(task) = self.find_task(queue, idx) {
+                // Another worker looks for the remaining tasks, if any
+                if searching && self.num_searching.fetch_sub(1, Ordering::SeqCst) == 1 {
+                    self.notify_idle();
+                }
+                return Some(task);
+            }
+
+            if self.closed.load(Ordering::SeqCst) {
+                return None;
+            }
+
+            {
+                let mut sleepers = self.sleepers.lock().unwrap();
+                sleepers.push(idx);
+                self.num_sleeping.fetch_add(1, Ordering::SeqCst);
+            }
+            if searching {
+                searching = false;
+                self.num_searching.fetch_sub(1, Ordering::SeqCst);
+            }
+
+            atomic::fence(Ordering::SeqCst);
+            if (self.has_tasks() || self.closed.load(Ordering::SeqCst)) && self.cancel_sleep(idx) {
+                continue;
+            }
+
+            // Either nobody took this worker off the sleepers yet, or the
+            // wakeup of whoever did is on its way.
+            self.parkers[idx].park();
+            if self.closed.load(Ordering::SeqCst) {
+                return None;
+            }
+            searching = true;
+        }
+    }
+
+    // Takes the worker `idx` off the sleepers, returning `false` if it has
+    // already been woken up.
+    fn cancel_sleep(&self, idx: usize) -> bool {
+        let mut sleepers = self.sleepers.lock().unwrap();
+        match sleepers.iter().position(|sleeper| *sleeper == idx) {
+            Some(pos) => {
+                sleepers.swap_remove(pos);
+                self.num_sleeping.fetch_sub(1, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Looks for a task in the worker's own queue, then in the injector, and
+    // finally steals some from the other workers. Tasks are taken from the
+    // injector and stolen in batches, to come back for each of them less
+    // often.
+    fn find_task(&self, queue: &Worker<Task>, idx: usize) -> Option<Task> {
+        queue.pop().or_else(|| {
+            iter::repeat_with(|| {
+                self.injector.steal_batch_and_pop(queue).or_else(|| {
+                    let num_workers = self.stealers.len();
+                    (1..num_workers)
+                        .map(|i| &self.stealers[(idx + i) % num_workers])
+                        .map(|stealer| stealer.steal_batch_and_pop(queue))
+                        .collect()
+                })
+            })
+            // Start over if stealing had to be retried because of contention
+            .find(|steal| !steal.is_retry())
+            .and_then(|steal| steal.success())
+        })
+    }
+
+    fn has_tasks(&self) -> bool {
+        !self.injector.is_empty() || self.stealers.iter().any(|stealer| !stealer.is_empty())
+    }
+}
+
+impl Parker {
+    fn new() -> Parker {
+        Parker {
+            notified: Mutex::new(false),
+            cvar: Condvar::new(),
+        }
+    }
+
+    fn park(&self) {
+        let mut notified = self.notified.lock().unwrap();
+        while !*notified {
+            notified = self.cvar.wait(notified).unwrap();
+        }
+        *notified = false;
+    }
+
+    fn unpark(&self) {
+        *self.notified.lock().unwrap() = true;
+        self.cvar.notify_one();
     }
 }
 
@@ -153,9 +486,7 @@ impl Clone for ThreadPool {
 impl Drop for ThreadPool {
     fn drop(&mut self) {
         if self.state.cnt.fetch_sub(1, Ordering::Relaxed) == 1 {
-            for _ in 0..self.state.size {
-                self.state.send(Message::Close);
-            }
+            self.state.close();
         }
     }
 }
@@ -171,6 +502,7 @@ impl ThreadPoolBuilder {
             name_prefix: None,
             after_start: None,
             before_stop: None,
+            panic_handler: None,
         }
     }
 
@@ -233,24 +565,55 @@ impl ThreadPoolBuilder {
         self
     }
 
+    /// Execute closure `f` with the payload of the panic whenever a task
+    /// panics while being polled.
+    ///
+    /// The panicking task is dropped, but the worker thread running it keeps
+    /// running the other tasks. By default, the panic is only reported by the
+    /// panic hook, as for any other panic.
+    ///
+    /// The closure runs on the worker thread on which the task panicked. If
+    /// it panics itself, that worker thread stops.
+    ///
+    /// Tasks spawned with `spawn_with_handle` catch their own panics, which
+    /// are returned by their `JoinHandle` as a `JoinError::Panicked` instead,
+    /// so the closure isn't called for them.
+    pub fn panic_handler<F>(&mut self, f: F) -> &mut Self
+        where F: Fn(Box<dyn Any + Send>) + Send + Sync + 'static
+    {
+        self.panic_handler = Some(Arc::new(f));
+        self
+    }
+
     /// Create a [`ThreadPool`](ThreadPool) with the given configuration.
     ///
     /// # Panics
     ///
     /// Panics if `pool_size == 0`.
     pub fn create(&mut self) -> Result<ThreadPool, io::Error> {
-        let (tx, rx) = mpsc::channel();
+        assert!(self.pool_size > 0);
+        let queues = (0..self.pool_size).map(|_| Worker::new_fifo()).collect::<Vec<_>>();
         let pool = ThreadPool {
             state: Arc::new(PoolState {
-                tx: Mutex::new(tx),
-                rx: Mutex::new(rx),
+                injector: Injector::new(),
+                stealers: queues.iter().map(Worker::stealer).collect(),
+                parkers: (0..self.pool_size).map(|_| Parker::new()).collect(),
+                sleepers: Mutex::new(Vec::with_capacity(self.pool_size)),
+                num_sleeping: AtomicUsize::new(0),
+                num_searching: AtomicUsize::new(0),
+                closed: AtomicBool::new(false),
+                is_shutdown: AtomicBool::new(false),
+                shutdown_on_idle: AtomicBool::new(false),
+                num_tasks: AtomicUsize::new(0),
+                idle_wakers: WakerSet::new(),
+                threads: Mutex::new(Vec::with_capacity(self.pool_size)),
+                panic_handler: self.panic_handler.clone(),
                 cnt: AtomicUsize::new(1),
                 size: self.pool_size,
             }),
         };
-        assert!(self.pool_size > 0);
 
-        for counter in 0..self.pool_size {
+        for (counter, queue) in queues.into_iter().enumerate() {
             let state = pool.state.clone();
             let after_start = self.after_start.clone();
             let before_stop = self.before_stop.clone();
@@ -261,7 +624,10 @@ impl ThreadPoolBuilder {
             if self.stack_size > 0 {
                 thread_builder = thread_builder.stack_size(self.stack_size);
             }
-            thread_builder.spawn(move || state.work(counter, after_start, before_stop))?;
+            let thread = thread_builder.spawn(move || {
+                state.work(counter, queue, after_start, before_stop)
+            })?;
+            pool.state.threads.lock().unwrap().push(thread);
         }
         Ok(pool)
     }
@@ -273,6 +639,49 @@ impl Default for ThreadPoolBuilder {
     }
 }
 
+/// Future returned by [`ThreadPool::shutdown_on_idle`], which resolves once
+/// all the tasks spawned on the pool have completed.
+#[must_use = "futures do nothing unless polled"]
+pub struct ShutdownOnIdle {
+    state: Arc<PoolState>,
+    wait_key: Option<usize>,
+}
+
+impl fmt::Debug for ShutdownOnIdle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ShutdownOnIdle")
+            .field("num_tasks", &self.state.num_tasks.load(Ordering::SeqCst))
+            .finish()
+    }
+}
+
+// Pinning is never projected to fields
+impl Unpin for ShutdownOnIdle {}
+
+impl Future for ShutdownOnIdle {
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<()> {
+        let this = &mut *self;
+        // Register before checking, so that no wakeup is lost
+        this.state.idle_wakers.register(&mut this.wait_key, cx.waker());
+        if this.state.num_tasks.load(Ordering::SeqCst) != 0 {
+            return Poll::Pending;
+        }
+
+        this.state.idle_wakers.remove(this.wait_key.take().unwrap());
+        Poll::Ready(())
+    }
+}
+
+impl Drop for ShutdownOnIdle {
+    fn drop(&mut self) {
+        if let Some(wait_key) = self.wait_key {
+            self.state.idle_wakers.remove(wait_key);
+        }
+    }
+}
+
 /// A task responsible for polling a future to completion.
 struct Task {
     future: FutureObj<'static, ()>,
@@ -300,11 +709,23 @@ impl Task {
             loop {
                 let res = {
                     let mut cx = task::Context::new(&local_waker, &mut exec);
-                    future.poll_unpin(&mut cx)
+                    panic::catch_unwind(AssertUnwindSafe(|| future.poll_unpin(&mut cx)))
                 };
                 match res {
-                    Poll::Pending => {}
-                    Poll::Ready(()) => return wake_handle.mutex.complete(),
+                    Ok(Poll::Pending) => {}
+                    Ok(Poll::Ready(())) => {
+                        wake_handle.mutex.complete();
+                        drop(future);
+                        return exec.state.complete_task();
+                    }
+                    Err(payload) => {
+                        // The future can't be polled anymore, later wakeups
+                        // are ignored.
+                        wake_handle.mutex.complete();
+                        drop(future);
+                        exec.state.handle_panic(payload);
+                        return exec.state.complete_task();
+                    }
                 }
                 let task = Task {
                     future,
@@ -334,7 +755,7 @@ impl fmt::Debug for Task {
 impl Wake for WakeHandle {
     fn wake(arc_self: &Arc<Self>) {
         match arc_self.mutex.notify() {
-            Ok(task) => arc_self.exec.state.send(Message::Run(task)),
+            Ok(task) => arc_self.exec.state.schedule(task),
             Err(()) => {}
         }
     }
@@ -1,12 +1,15 @@
-#![feature(pin, arbitrary_self_types, futures_api)]
+#![feature(async_await, await_macro, pin, arbitrary_self_types, futures_api)]
 
 use futures::channel::oneshot;
-use futures::executor::LocalPool;
-use futures::future::{Future, lazy};
-use futures::task::{self, Poll, Spawn};
+use futures::executor::{block_on, LocalPool};
+use futures::future::{self, Future, lazy};
+use futures::task::{self, JoinError, Poll, Spawn, SpawnExt, Wake};
 use std::cell::{Cell, RefCell};
 use std::pin::{PinBox, PinMut};
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
 struct Pending(Rc<()>);
 
@@ -170,3 +173,121 @@ fn tasks_are_scheduled_fairly() {
     pool.run(&mut spawn);
 }
 
+
+#[test]
+fn run_until_stalled_returns_pending_tasks() {
+    let cnt = Rc::new(Cell::new(0));
+    let cnt2 = cnt.clone();
+
+    let mut pool = LocalPool::new();
+    let mut spawn = pool.spawner();
+    let (tx, rx) = oneshot::channel::<()>();
+
+    spawn.spawn_local_obj(PinBox::new(pending()).into()).unwrap();
+    spawn.spawn_local_obj(PinBox::new(async move {
+        await!(rx).unwrap();
+        cnt2.set(cnt2.get() + 1);
+    }).into()).unwrap();
+
+    assert_eq!(pool.run_until_stalled(&mut spawn), 2);
+    assert_eq!(cnt.get(), 0);
+
+    tx.send(()).unwrap();
+    assert_eq!(pool.run_until_stalled(&mut spawn), 1);
+    assert_eq!(cnt.get(), 1);
+}
+
+#[test]
+fn try_run_one_completes_one_task() {
+    let cnt = Rc::new(Cell::new(0));
+
+    let mut pool = LocalPool::new();
+    let mut spawn = pool.spawner();
+
+    for _ in 0..3 {
+        let cnt = cnt.clone();
+        spawn.spawn_local_obj(PinBox::new(lazy(move |_| {
+            cnt.set(cnt.get() + 1);
+            ()
+        })).into()).unwrap();
+    }
+
+    assert_eq!(pool.try_run_one(&mut spawn), 2);
+    assert_eq!(cnt.get(), 1);
+    assert_eq!(pool.try_run_one(&mut spawn), 1);
+    assert_eq!(pool.try_run_one(&mut spawn), 0);
+    assert_eq!(pool.try_run_one(&mut spawn), 0);
+    assert_eq!(cnt.get(), 3);
+}
+
+#[test]
+fn poll_once_wakes_until_stalled() {
+    struct WakeCounter(AtomicUsize);
+
+    impl Wake for WakeCounter {
+        fn wake(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let wake_counter = Arc::new(WakeCounter(AtomicUsize::new(0)));
+    let local_waker = task::local_waker_from_nonlocal(wake_counter.clone());
+
+    let mut pool = LocalPool::new();
+    let mut spawn = pool.spawner();
+    let mut cx = task::Context::new(&local_waker, &mut spawn);
+
+    pool.spawner().spawn_local_obj(PinBox::new(pending()).into()).unwrap();
+    pool.spawner().spawn_local_obj(PinBox::new(lazy(|_| ())).into()).unwrap();
+
+    // A task completed, the pool asks to be polled again
+    assert_eq!(pool.poll_once(&mut cx), 1);
+    assert_eq!(wake_counter.0.load(Ordering::SeqCst), 1);
+
+    // The remaining task is stalled
+    assert_eq!(pool.poll_once(&mut cx), 1);
+    assert_eq!(wake_counter.0.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn remote_spawner_wakes_pool() {
+    let mut pool = LocalPool::new();
+    let mut spawn = pool.spawner();
+    let mut remote = pool.remote_spawner();
+    let (tx, rx) = oneshot::channel();
+
+    // The pool is blocked waiting for `rx` when the task is spawned
+    let handle = thread::spawn(move || {
+        remote.spawn(async move {
+            tx.send(1).unwrap();
+        }).unwrap();
+    });
+
+    assert_eq!(pool.run_until(rx, &mut spawn), Ok(1));
+    handle.join().unwrap();
+}
+
+#[test]
+fn remote_spawner_shutdown() {
+    let pool = LocalPool::new();
+    let mut remote = pool.remote_spawner();
+    assert!(remote.status().is_ok());
+
+    drop(pool);
+    assert!(remote.status().unwrap_err().is_shutdown());
+    assert!(remote.spawn(async {}).unwrap_err().kind.is_shutdown());
+}
+
+#[test]
+fn join_handle_canceled_on_pool_drop() {
+    let pool = LocalPool::new();
+    let mut spawn = pool.spawner();
+
+    let handle = spawn.spawn_with_handle(future::empty::<i32>()).unwrap();
+    drop(pool);
+
+    match block_on(handle) {
+        Err(JoinError::Canceled) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
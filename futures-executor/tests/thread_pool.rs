@@ -0,0 +1,189 @@
+#![feature(async_await, await_macro, futures_api, pin, arbitrary_self_types)]
+
+use futures::channel::{mpsc, oneshot};
+use futures::executor::{block_on, ThreadPool};
+use futures::future;
+use futures::stream::StreamExt;
+use futures::task::{JoinError, Spawn, SpawnExt};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc as std_mpsc;
+
+#[test]
+fn run_nested_spawns() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder()
+        .pool_size(4)
+        .create()
+        .unwrap();
+
+    let num_parents = 10;
+    let num_children = 100;
+    for _ in 0..num_parents {
+        let tx = tx.clone();
+        let mut spawner = pool.clone();
+        pool.clone().spawn(async move {
+            for _ in 0..num_children {
+                let tx = tx.clone();
+                spawner.spawn(async move {
+                    tx.unbounded_send(()).unwrap();
+                }).unwrap();
+            }
+        }).unwrap();
+    }
+
+    block_on(async {
+        for _ in 0..num_parents * num_children {
+            let () = await!(rx.next()).unwrap();
+        }
+    })
+}
+
+#[test]
+fn wake_across_workers() {
+    let pool = ThreadPool::builder()
+        .pool_size(4)
+        .create()
+        .unwrap();
+
+    // A chain of tasks, each waiting for the previous one to wake it up
+    let (first_tx, mut rx) = oneshot::channel::<usize>();
+    for _ in 0..1000 {
+        let (tx, next_rx) = oneshot::channel();
+        pool.clone().spawn(async move {
+            let n = await!(rx).unwrap();
+            tx.send(n + 1).unwrap();
+        }).unwrap();
+        rx = next_rx;
+    }
+
+    first_tx.send(0).unwrap();
+    assert_eq!(block_on(rx), Ok(1000));
+}
+
+#[test]
+fn workers_stop_on_drop() {
+    let (tx, rx) = std_mpsc::sync_channel(3);
+    let started = Arc::new(AtomicUsize::new(0));
+    let started2 = started.clone();
+    let pool = ThreadPool::builder()
+        .pool_size(3)
+        .after_start(move |_| { started2.fetch_add(1, Ordering::SeqCst); })
+        .before_stop(move |idx| tx.send(idx).unwrap())
+        .create()
+        .unwrap();
+
+    drop(pool);
+
+    let mut stopped = rx.into_iter().collect::<Vec<_>>();
+    stopped.sort();
+    assert_eq!(stopped, vec![0, 1, 2]);
+    assert_eq!(started.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn panicking_task_keeps_worker() {
+    let (tx, rx) = std_mpsc::sync_channel(1);
+    let mut pool = ThreadPool::builder()
+        .pool_size(1)
+        .panic_handler(move |payload| {
+            tx.send(*payload.downcast::<&str>().unwrap()).unwrap();
+        })
+        .create()
+        .unwrap();
+
+    pool.spawn(async {
+        panic!("task panicked");
+    }).unwrap();
+    assert_eq!(rx.recv().unwrap(), "task panicked");
+
+    // The only worker thread is still running tasks
+    let handle = pool.spawn_with_handle(future::ready(1)).unwrap();
+    assert_eq!(block_on(handle).unwrap(), 1);
+}
+
+#[test]
+fn join_handle_returns_panic() {
+    let mut pool = ThreadPool::builder()
+        .pool_size(1)
+        .create()
+        .unwrap();
+
+    let handle = pool.spawn_with_handle(async {
+        if true {
+            panic!("task panicked");
+        }
+        1
+    }).unwrap();
+    match block_on(handle) {
+        Err(JoinError::Panicked(payload)) => {
+            assert_eq!(*payload.downcast::<&str>().unwrap(), "task panicked");
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn shutdown_refuses_spawns() {
+    let mut pool = ThreadPool::builder()
+        .pool_size(2)
+        .create()
+        .unwrap();
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let handle = pool.spawn_with_handle(async {
+        await!(rx).unwrap();
+        1
+    }).unwrap();
+
+    pool.shutdown();
+    assert!(pool.status().unwrap_err().is_shutdown());
+    assert!(pool.spawn(async {}).unwrap_err().kind.is_shutdown());
+
+    // Tasks spawned before the shutdown still run to completion
+    tx.send(()).unwrap();
+    assert_eq!(block_on(handle).unwrap(), 1);
+
+    pool.join();
+}
+
+#[test]
+fn shutdown_on_idle_waits_for_tasks() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let mut pool = ThreadPool::builder()
+        .pool_size(4)
+        .create()
+        .unwrap();
+
+    let num_tasks = 100;
+    for i in 0..num_tasks {
+        let tx = tx.clone();
+        pool.spawn(async move {
+            tx.unbounded_send(i).unwrap();
+        }).unwrap();
+    }
+    drop(tx);
+
+    block_on(pool.shutdown_on_idle());
+    assert!(pool.spawn(async {}).unwrap_err().kind.is_shutdown());
+
+    let mut sent = block_on(rx.collect::<Vec<_>>());
+    sent.sort();
+    assert_eq!(sent, (0..num_tasks).collect::<Vec<_>>());
+    pool.join();
+}
+
+#[test]
+fn join_waits_for_hooks() {
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let stopped2 = stopped.clone();
+    let pool = ThreadPool::builder()
+        .pool_size(3)
+        .before_stop(move |_| { stopped2.fetch_add(1, Ordering::SeqCst); })
+        .create()
+        .unwrap();
+
+    pool.shutdown();
+    pool.join();
+    assert_eq!(stopped.load(Ordering::SeqCst), 3);
+}
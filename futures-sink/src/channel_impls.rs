@@ -1,5 +1,6 @@
 use crate::{Sink, Poll};
 use futures_core::task;
+use futures_channel::{broadcast, lossy, mpmc, priority, rendezvous, watch};
 use futures_channel::mpsc::{Sender, SendError, UnboundedSender};
 use std::pin::PinMut;
 
@@ -69,3 +70,184 @@ impl<'a, T> Sink for &'a UnboundedSender<T> {
         Poll::Ready(Ok(()))
     }
 }
+
+impl<T> Sink for mpmc::Sender<T> {
+    type SinkItem = T;
+    type SinkError = SendError;
+
+    fn poll_ready(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        (*self).poll_ready(cx)
+    }
+
+    fn start_send(mut self: PinMut<Self>, msg: T) -> Result<(), Self::SinkError> {
+        (*self).start_send(msg)
+    }
+
+    fn poll_flush(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        self.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Sink for mpmc::UnboundedSender<T> {
+    type SinkItem = T;
+    type SinkError = SendError;
+
+    fn poll_ready(self: PinMut<Self>, cx: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        mpmc::UnboundedSender::poll_ready(&*self, cx)
+    }
+
+    fn start_send(mut self: PinMut<Self>, msg: T) -> Result<(), Self::SinkError> {
+        mpmc::UnboundedSender::start_send(&mut *self, msg)
+    }
+
+    fn poll_flush(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        self.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<'a, T> Sink for &'a mpmc::UnboundedSender<T> {
+    type SinkItem = T;
+    type SinkError = SendError;
+
+    fn poll_ready(self: PinMut<Self>, cx: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        mpmc::UnboundedSender::poll_ready(*self, cx)
+    }
+
+    fn start_send(self: PinMut<Self>, msg: T) -> Result<(), Self::SinkError> {
+        self.unbounded_send(msg)
+            .map_err(|err| err.into_send_error())
+    }
+
+    fn poll_flush(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        self.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<P: Ord, T> Sink for priority::Sender<P, T> {
+    type SinkItem = (P, T);
+    type SinkError = SendError;
+
+    fn poll_ready(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        (*self).poll_ready(cx)
+    }
+
+    fn start_send(mut self: PinMut<Self>, (priority, msg): (P, T)) -> Result<(), Self::SinkError> {
+        (*self).start_send(priority, msg)
+    }
+
+    fn poll_flush(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        self.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Sink for rendezvous::Sender<T> {
+    type SinkItem = T;
+    type SinkError = SendError;
+
+    fn poll_ready(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        (*self).poll_ready(cx)
+    }
+
+    fn start_send(mut self: PinMut<Self>, msg: T) -> Result<(), Self::SinkError> {
+        (*self).start_send(msg)
+    }
+
+    fn poll_flush(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        (*self).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        // Closing waits for the last message to be received, like flushing
+        match (*self).poll_flush(cx) {
+            Poll::Ready(Ok(())) => {}
+            other => return other,
+        }
+        self.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Sink for lossy::Sender<T> {
+    type SinkItem = T;
+    type SinkError = lossy::SendError<T>;
+
+    fn poll_ready(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: PinMut<Self>, msg: T) -> Result<(), Self::SinkError> {
+        self.send(msg)
+    }
+
+    fn poll_flush(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        self.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Sink for broadcast::Sender<T> {
+    type SinkItem = T;
+    type SinkError = broadcast::SendError<T>;
+
+    fn poll_ready(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: PinMut<Self>, msg: T) -> Result<(), Self::SinkError> {
+        self.send(msg).map(|_| ())
+    }
+
+    fn poll_flush(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        self.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Sink for watch::Sender<T> {
+    type SinkItem = T;
+    type SinkError = watch::SendError<T>;
+
+    fn poll_ready(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: PinMut<Self>, value: T) -> Result<(), Self::SinkError> {
+        self.broadcast(value)
+    }
+
+    fn poll_flush(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: PinMut<Self>, _: &mut task::Context) -> Poll<Result<(), Self::SinkError>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -34,7 +34,9 @@ macro_rules! spawn {
 /// [`JoinHandle`](crate::task::JoinHandle), or, if spawning fails, a
 /// [`SpawnError`](crate::task::SpawnError).
 /// [`JoinHandle`](crate::task::JoinHandle) is a future that resolves
-/// to the output of the spawned future
+/// to the output of the spawned future, or to a
+/// [`JoinError`](crate::task::JoinError) if it panicked or was dropped
+/// before completing.
 ///
 /// # Examples
 ///
@@ -42,10 +44,15 @@ macro_rules! spawn {
 /// #![feature(async_await, await_macro, futures_api)]
 /// # futures::executor::block_on(async {
 /// use futures::{future, spawn_with_handle};
+/// use futures::task::JoinError;
 ///
 /// let future = future::ready(1);
 /// let join_handle = spawn_with_handle!(future).unwrap();
-/// assert_eq!(await!(join_handle), 1);
+/// match await!(join_handle) {
+///     Ok(output) => assert_eq!(output, 1),
+///     Err(JoinError::Panicked(_)) => panic!("the task panicked"),
+///     Err(JoinError::Canceled) => panic!("the task was dropped"),
+/// }
 /// # });
 /// ```
 #[macro_export]
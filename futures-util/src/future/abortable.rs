@@ -1,27 +1,38 @@
 use crate::task::AtomicWaker;
+use super::CancellationToken;
 use futures_core::future::Future;
+use futures_core::stream::Stream;
 use futures_core::task::{self, Poll};
+use futures_sink::Sink;
 use pin_utils::unsafe_pinned;
+use std::error::Error;
+use std::fmt;
 use std::marker::Unpin;
 use std::pin::PinMut;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-/// A future which can be remotely short-circuited using an `AbortHandle`.
+/// A future, stream or sink which can be remotely short-circuited using an
+/// `AbortHandle`.
+///
+/// Once aborted, an `Abortable` future resolves to `Err(Aborted)`, an
+/// `Abortable` stream terminates, and an `Abortable` sink fails with
+/// `AbortableSinkError::Aborted`.
 #[derive(Debug, Clone)]
 #[must_use = "futures do nothing unless polled"]
-pub struct Abortable<Fut> {
-    future: Fut,
+pub struct Abortable<T> {
+    task: T,
     inner: Arc<AbortInner>,
 }
 
-impl<Fut: Unpin> Unpin for Abortable<Fut> {}
+impl<T: Unpin> Unpin for Abortable<T> {}
 
-impl<Fut> Abortable<Fut> where Fut: Future {
-    unsafe_pinned!(future: Fut);
+impl<T> Abortable<T> {
+    unsafe_pinned!(task: T);
 
-    /// Creates a new `Abortable` future using an existing `AbortRegistration`.
-    /// `AbortRegistration`s can be acquired through `AbortHandle::new`.
+    /// Creates a new `Abortable` future, stream or sink using an existing
+    /// `AbortRegistration`. `AbortRegistration`s can be acquired through
+    /// `AbortHandle::new`.
     ///
     /// When `abort` is called on the handle tied to `reg` or if `abort` has
     /// already been called, the future will complete immediately without making
@@ -38,12 +49,72 @@ impl<Fut> Abortable<Fut> where Fut: Future {
     /// abort_handle.abort();
     /// assert_eq!(block_on(future), Err(Aborted));
     /// ```
-    pub fn new(future: Fut, reg: AbortRegistration) -> Self {
+    pub fn new(task: T, reg: AbortRegistration) -> Self {
         Abortable {
-            future,
+            task,
             inner: reg.inner,
         }
     }
+
+    /// Creates a new `Abortable` future, stream or sink which is aborted when
+    /// `token` is cancelled.
+    ///
+    /// If `token` has already been cancelled, the future will complete
+    /// immediately without making any progress. Unlike an `AbortHandle`, a
+    /// token can abort any number of futures, streams and sinks at once.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use futures::future::{ready, Abortable, Aborted, CancellationToken};
+    /// use futures::executor::block_on;
+    ///
+    /// let token = CancellationToken::new();
+    /// let future = Abortable::with_token(ready(2), &token.child_token());
+    /// token.cancel();
+    /// assert_eq!(block_on(future), Err(Aborted));
+    /// ```
+    pub fn with_token(task: T, token: &CancellationToken) -> Self {
+        let (_, reg) = AbortHandle::new_pair();
+        token.register_abort(&reg.inner);
+        Abortable::new(task, reg)
+    }
+
+    /// Returns `true` if this `Abortable` has been aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.inner.cancel.load(Ordering::Relaxed)
+    }
+
+    // Polls the wrapped future, stream or sink with `poll` unless it has been
+    // aborted, registering to receive a wakeup if it is aborted while pending.
+    fn poll_unless_aborted<R>(
+        mut self: PinMut<Self>,
+        cx: &mut task::Context,
+        poll: impl FnOnce(PinMut<T>, &mut task::Context) -> Poll<R>,
+    ) -> Poll<Result<R, Aborted>> {
+        // Check if the task has been aborted
+        if self.is_aborted() {
+            return Poll::Ready(Err(Aborted))
+        }
+
+        // attempt to complete the task
+        if let Poll::Ready(x) = poll(self.task(), cx) {
+            return Poll::Ready(Ok(x))
+        }
+
+        // Register to receive a wakeup if the task is aborted in the... future
+        self.inner.waker.register(cx.waker());
+
+        // Check to see if the task was aborted between the first check and
+        // registration.
+        // Checking with `Relaxed` is sufficient because `register` introduces an
+        // `AcqRel` barrier.
+        if self.is_aborted() {
+            return Poll::Ready(Err(Aborted))
+        }
+
+        Poll::Pending
+    }
 }
 
 /// A registration handle for a `Abortable` future.
@@ -96,7 +167,7 @@ impl AbortHandle {
 // Inner type storing the waker to awaken and a bool indicating that it
 // should be cancelled.
 #[derive(Debug)]
-struct AbortInner {
+pub(super) struct AbortInner {
     waker: AtomicWaker,
     cancel: AtomicBool,
 }
@@ -119,32 +190,101 @@ pub fn abortable<Fut>(future: Fut) -> (Abortable<Fut>, AbortHandle)
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Aborted;
 
-impl<Fut> Future for Abortable<Fut> where Fut: Future {
-    type Output = Result<Fut::Output, Aborted>;
+/// Error returned by an `Abortable` sink.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AbortableSinkError<E> {
+    /// The sink was aborted.
+    Aborted,
+    /// The underlying sink failed.
+    Sink(E),
+}
 
-    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
-        // Check if the future has been aborted
-        if self.inner.cancel.load(Ordering::Relaxed) {
-            return Poll::Ready(Err(Aborted))
+impl<E: fmt::Display> fmt::Display for AbortableSinkError<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AbortableSinkError::Aborted => write!(fmt, "sink was aborted"),
+            AbortableSinkError::Sink(e) => write!(fmt, "sink failed: {}", e),
         }
+    }
+}
 
-        // attempt to complete the future
-        if let Poll::Ready(x) = self.future().poll(cx) {
-            return Poll::Ready(Ok(x))
+impl<E: Error> Error for AbortableSinkError<E> {
+    fn description(&self) -> &str {
+        match self {
+            AbortableSinkError::Aborted => "sink was aborted",
+            AbortableSinkError::Sink(e) => e.description(),
         }
+    }
 
-        // Register to receive a wakeup if the future is aborted in the... future
-        self.inner.waker.register(cx.waker());
+    fn cause(&self) -> Option<&dyn Error> {
+        match self {
+            AbortableSinkError::Aborted => None,
+            AbortableSinkError::Sink(e) => Some(e),
+        }
+    }
+}
 
-        // Check to see if the future was aborted between the first check and
-        // registration.
-        // Checking with `Relaxed` is sufficient because `register` introduces an
-        // `AcqRel` barrier.
-        if self.inner.cancel.load(Ordering::Relaxed) {
-            return Poll::Ready(Err(Aborted))
+impl<Fut> Future for Abortable<Fut> where Fut: Future {
+    type Output = Result<Fut::Output, Aborted>;
+
+    fn poll(self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        self.poll_unless_aborted(cx, |future, cx| future.poll(cx))
+    }
+}
+
+impl<St> Stream for Abortable<St> where St: Stream {
+    type Item = St::Item;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut task::Context) -> Poll<Option<St::Item>> {
+        self.poll_unless_aborted(cx, |stream, cx| stream.poll_next(cx))
+            .map(|next| next.unwrap_or(None))
+    }
+}
+
+impl<Si> Sink for Abortable<Si> where Si: Sink {
+    type SinkItem = Si::SinkItem;
+    type SinkError = AbortableSinkError<Si::SinkError>;
+
+    fn poll_ready(
+        self: PinMut<Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Result<(), Self::SinkError>> {
+        self.poll_unless_aborted(cx, |sink, cx| sink.poll_ready(cx)).map(flatten_sink_result)
+    }
+
+    fn start_send(
+        mut self: PinMut<Self>,
+        item: Self::SinkItem,
+    ) -> Result<(), Self::SinkError> {
+        if self.is_aborted() {
+            return Err(AbortableSinkError::Aborted)
         }
+        self.task().start_send(item).map_err(AbortableSinkError::Sink)
+    }
 
-        Poll::Pending
+    fn poll_flush(
+        self: PinMut<Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Result<(), Self::SinkError>> {
+        self.poll_unless_aborted(cx, |sink, cx| sink.poll_flush(cx)).map(flatten_sink_result)
+    }
+
+    // Closing isn't aborted, so that the underlying sink can still be shut
+    // down cleanly.
+    fn poll_close(
+        mut self: PinMut<Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Result<(), Self::SinkError>> {
+        self.task().poll_close(cx).map(|res| res.map_err(AbortableSinkError::Sink))
+    }
+}
+
+fn flatten_sink_result<E>(
+    res: Result<Result<(), E>, Aborted>,
+) -> Result<(), AbortableSinkError<E>> {
+    match res {
+        Ok(res) => res.map_err(AbortableSinkError::Sink),
+        Err(Aborted) => Err(AbortableSinkError::Aborted),
     }
 }
 
@@ -156,7 +296,13 @@ impl AbortHandle {
     /// another thread, it will not immediately stop running. Instead, it will
     /// continue to run until its poll method returns.
     pub fn abort(&self) {
-        self.inner.cancel.store(true, Ordering::Relaxed);
-        self.inner.waker.wake();
+        self.inner.abort();
+    }
+}
+
+impl AbortInner {
+    pub(super) fn abort(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.waker.wake();
     }
 }
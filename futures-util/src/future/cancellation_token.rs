@@ -0,0 +1,226 @@
+use crate::task::WakerSet;
+use futures_core::future::Future;
+use futures_core::task::{self, Poll};
+use std::marker::Unpin;
+use std::pin::PinMut;
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::vec::Vec;
+
+use super::abortable::AbortInner;
+
+/// A token signalling cancellation to the tasks holding it.
+///
+/// Tokens form a tree: [`child_token`](CancellationToken::child_token)
+/// creates a token which is cancelled along with its parent, but which can
+/// also be cancelled on its own without affecting the parent. Cancelling the
+/// root token of a subsystem cancels everything running under it.
+///
+/// Tasks can wait for cancellation with
+/// [`cancelled`](CancellationToken::cancelled), or tie a future or a stream to
+/// a token with [`Abortable::with_token`](super::Abortable::with_token) and
+/// [`StreamExt::take_until_cancelled`](crate::stream::StreamExt::take_until_cancelled).
+///
+/// Clones of a token are the same token: cancelling one cancels all of them.
+#[derive(Debug, Clone)]
+pub struct CancellationToken {
+    inner: Arc<TokenInner>,
+}
+
+#[derive(Debug)]
+struct TokenInner {
+    is_cancelled: AtomicBool,
+    // Tasks waiting for cancellation
+    wakers: WakerSet,
+    // What to cancel along with this token. Set to `None` on cancellation.
+    dependents: Mutex<Option<Dependents>>,
+}
+
+// Child tokens are kept alive by their parent even once all their clones are
+// dropped, as they still have to cancel what is tied to them.
+#[derive(Debug, Default)]
+struct Dependents {
+    children: Vec<Arc<TokenInner>>,
+    aborts: Vec<Weak<AbortInner>>,
+}
+
+impl CancellationToken {
+    /// Creates a new root token, which isn't cancelled.
+    pub fn new() -> CancellationToken {
+        CancellationToken {
+            inner: Arc::new(TokenInner {
+                is_cancelled: AtomicBool::new(false),
+                wakers: WakerSet::new(),
+                dependents: Mutex::new(Some(Dependents::default())),
+            }),
+        }
+    }
+
+    /// Creates a child token, which is cancelled when this token is.
+    ///
+    /// The child is cancelled right away if this token already is.
+    pub fn child_token(&self) -> CancellationToken {
+        let child = CancellationToken::new();
+
+        let mut dependents = self.inner.dependents.lock().unwrap();
+        match &mut *dependents {
+            Some(dependents) => {
+                if dependents.children.len() == dependents.children.capacity() {
+                    dependents.prune();
+                }
+                dependents.children.push(child.inner.clone());
+            }
+            None => {
+                drop(dependents);
+                child.cancel();
+            }
+        }
+
+        child
+    }
+
+    /// Cancels this token and all its descendants, waking up the tasks
+    /// waiting for their cancellation.
+    ///
+    /// Cancelling a token more than once has no effect.
+    pub fn cancel(&self) {
+        let mut stack = vec![self.inner.clone()];
+
+        while let Some(inner) = stack.pop() {
+            let dependents = match inner.dependents.lock().unwrap().take() {
+                Some(dependents) => dependents,
+                // Already cancelled
+                None => continue,
+            };
+
+            inner.is_cancelled.store(true, Ordering::SeqCst);
+            inner.wakers.wake_all();
+
+            for abort in dependents.aborts.iter().filter_map(Weak::upgrade) {
+                abort.abort();
+            }
+            stack.extend(dependents.children);
+        }
+    }
+
+    /// Returns `true` if this token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns a future which resolves once this token is cancelled.
+    pub fn cancelled(&self) -> WaitForCancellation<'_> {
+        WaitForCancellation {
+            token: self,
+            wait_key: None,
+        }
+    }
+
+    // Aborts the `Abortable` future tied to `abort` when this token is
+    // cancelled.
+    pub(super) fn register_abort(&self, abort: &Arc<AbortInner>) {
+        let mut dependents = self.inner.dependents.lock().unwrap();
+        match &mut *dependents {
+            Some(dependents) => {
+                if dependents.aborts.len() == dependents.aborts.capacity() {
+                    dependents.prune();
+                }
+                dependents.aborts.push(Arc::downgrade(abort));
+            }
+            None => {
+                drop(dependents);
+                abort.abort();
+            }
+        }
+    }
+
+    // Polls for cancellation on behalf of a future or stream, whose waker in
+    // the set of waiting tasks is tracked by `wait_key`.
+    pub(crate) fn poll_cancelled(
+        &self,
+        wait_key: &mut Option<usize>,
+        cx: &mut task::Context,
+    ) -> Poll<()> {
+        if !self.is_cancelled() {
+            // Register before checking again, so that no wakeup is lost
+            self.inner.wakers.register(wait_key, cx.waker());
+            if !self.is_cancelled() {
+                return Poll::Pending;
+            }
+        }
+
+        if let Some(key) = wait_key.take() {
+            self.inner.wakers.remove(key);
+        }
+        Poll::Ready(())
+    }
+
+    // Stops waiting for cancellation on behalf of a future or stream which is
+    // dropped.
+    pub(crate) fn unregister(&self, wait_key: usize) {
+        self.inner.wakers.remove(wait_key);
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> CancellationToken {
+        CancellationToken::new()
+    }
+}
+
+impl Dependents {
+    // Removes the dependents whose cancellation can't be observed anymore,
+    // returning `true` if none are left. This is only done once enough of
+    // them accumulated, to amortize the cost.
+    fn prune(&mut self) -> bool {
+        self.aborts.retain(|abort| abort.upgrade().is_some());
+        self.children.retain(|child| !is_unused(child));
+        self.aborts.is_empty() && self.children.is_empty()
+    }
+}
+
+// A child token is unused if only its parent holds it and nothing is tied to
+// it anymore.
+fn is_unused(inner: &Arc<TokenInner>) -> bool {
+    // Nobody can clone the token while we hold the parent's lock
+    if Arc::strong_count(inner) > 1 {
+        return false;
+    }
+
+    match &mut *inner.dependents.lock().unwrap() {
+        Some(dependents) => dependents.prune(),
+        None => true,
+    }
+}
+
+/// A future which resolves when the target
+/// [`CancellationToken`](CancellationToken) is cancelled.
+///
+/// This value is created by the
+/// [`cancelled`](CancellationToken::cancelled) method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitForCancellation<'a> {
+    token: &'a CancellationToken,
+    wait_key: Option<usize>,
+}
+
+// Pinning is never projected to fields
+impl<'a> Unpin for WaitForCancellation<'a> {}
+
+impl<'a> Future for WaitForCancellation<'a> {
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<()> {
+        let this = &mut *self;
+        this.token.poll_cancelled(&mut this.wait_key, cx)
+    }
+}
+
+impl<'a> Drop for WaitForCancellation<'a> {
+    fn drop(&mut self) {
+        if let Some(wait_key) = self.wait_key {
+            self.token.unregister(wait_key);
+        }
+    }
+}
@@ -71,7 +71,13 @@ if_std! {
     use std::pin::PinBox;
 
     mod abortable;
-    pub use self::abortable::{abortable, Abortable, AbortHandle, AbortRegistration, Aborted};
+    pub use self::abortable::{
+        abortable, Abortable, AbortHandle, AbortRegistration, Aborted,
+        AbortableSinkError,
+    };
+
+    mod cancellation_token;
+    pub use self::cancellation_token::{CancellationToken, WaitForCancellation};
 
     mod catch_unwind;
     pub use self::catch_unwind::CatchUnwind;
@@ -88,6 +94,9 @@ if_std! {
 
     mod shared;
     pub use self::shared::Shared;
+
+    mod single_flight;
+    pub use self::single_flight::{SingleFlight, SingleFlightCall};
 }
 
 impl<T: ?Sized> FutureExt for T where T: Future {}
@@ -86,6 +86,11 @@ impl<Fut: Future> Shared<Fut> {
             waker_key: NULL_WAKER_KEY,
         }
     }
+
+    /// Returns `true` if both `Shared`s are clones of the same future.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
 }
 
 impl<Fut> Shared<Fut>
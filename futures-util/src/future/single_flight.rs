@@ -0,0 +1,246 @@
+use futures_core::future::Future;
+use futures_core::task::{self, Poll};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::Unpin;
+use std::pin::PinMut;
+use std::sync::Mutex;
+
+use super::{FutureExt, Shared};
+
+/// Deduplicates concurrent calls for the same key.
+///
+/// When several tasks [`call`](SingleFlight::call) the group with the same key
+/// while a future for that key is in flight, the future is only created and
+/// run once, and all of them get a clone of its output. The future is shared
+/// with [`Shared`](super::Shared), and polled by whichever caller is polled.
+///
+/// By default, a key is evicted as soon as its future completes, so that the
+/// next call runs a new future. A group created with
+/// [`cache_ok`](SingleFlight::cache_ok) keeps successful outputs around
+/// instead, and only evicts failed calls.
+///
+/// If all the callers for a key are dropped before its future completes, the
+/// future is dropped and the key evicted.
+pub struct SingleFlight<K, Fut: Future> {
+    flights: Mutex<HashMap<K, Flight<Fut>>>,
+    // Whether to keep an output in the group once the future completed
+    keep: fn(&Fut::Output) -> bool,
+}
+
+struct Flight<Fut: Future> {
+    shared: Shared<Fut>,
+    // Number of `SingleFlightCall`s for this flight which haven't completed
+    callers: usize,
+}
+
+impl<K, Fut> SingleFlight<K, Fut>
+    where K: Eq + Hash + Clone,
+          Fut: Future,
+          Fut::Output: Clone,
+{
+    /// Creates a new group which evicts keys once their future completes.
+    pub fn new() -> SingleFlight<K, Fut> {
+        SingleFlight {
+            flights: Mutex::new(HashMap::new()),
+            keep: |_| false,
+        }
+    }
+
+    /// Calls the group for `key`, returning a future which resolves to the
+    /// output of the future in flight for that key.
+    ///
+    /// `f` is only called to create the future if no future is in flight for
+    /// `key`, nor cached. It is called without any lock held, so it can call
+    /// into the group itself. If a concurrent call for `key` creates its
+    /// future first, the future created by `f` is dropped without being
+    /// polled, and this call joins the other one.
+    pub fn call<F>(&self, key: K, f: F) -> SingleFlightCall<'_, K, Fut>
+        where F: FnOnce() -> Fut,
+    {
+        let shared = match self.join(&key) {
+            Some(shared) => shared,
+            None => {
+                let shared = f().shared();
+                let mut flights = self.flights.lock().unwrap();
+                let flight = flights.entry(key.clone()).or_insert_with(|| Flight {
+                    shared,
+                    callers: 0,
+                });
+                flight.callers += 1;
+                flight.shared.clone()
+            }
+        };
+
+        SingleFlightCall {
+            group: self,
+            key: Some(key),
+            shared,
+        }
+    }
+
+    /// Evicts `key` from the group, returning whether it was in the group.
+    ///
+    /// The callers of a future in flight for `key` still get its output, but
+    /// the next call for `key` creates a new future.
+    pub fn forget(&self, key: &K) -> bool {
+        self.flights.lock().unwrap().remove(key).is_some()
+    }
+
+    /// Returns the number of keys with a future in flight or an output cached.
+    pub fn len(&self) -> usize {
+        self.flights.lock().unwrap().len()
+    }
+
+    /// Returns `true` if no key has a future in flight or an output cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Joins the flight for `key`, if there is one
+    fn join(&self, key: &K) -> Option<Shared<Fut>> {
+        let mut flights = self.flights.lock().unwrap();
+        flights.get_mut(key).map(|flight| {
+            flight.callers += 1;
+            flight.shared.clone()
+        })
+    }
+
+    // Called once a caller got the output of its flight
+    fn complete(&self, key: &K, shared: &Shared<Fut>, output: &Fut::Output) {
+        let mut flights = self.flights.lock().unwrap();
+        let evict = match flights.get_mut(key) {
+            Some(flight) if flight.shared.ptr_eq(shared) => {
+                flight.callers -= 1;
+                !(self.keep)(output)
+            }
+            _ => false,
+        };
+        if evict {
+            flights.remove(key);
+        }
+    }
+
+    // Called when a caller is dropped before getting the output of its flight
+    fn cancel(&self, key: &K, shared: &Shared<Fut>) {
+        let mut flights = match self.flights.lock() {
+            Ok(flights) => flights,
+            Err(_) => return,
+        };
+        let evict = match flights.get_mut(key) {
+            Some(flight) if flight.shared.ptr_eq(shared) => {
+                flight.callers -= 1;
+                flight.callers == 0 && flight.shared.peek().is_none()
+            }
+            _ => false,
+        };
+        if evict {
+            flights.remove(key);
+        }
+    }
+}
+
+impl<K, Fut, T, E> SingleFlight<K, Fut>
+    where K: Eq + Hash + Clone,
+          Fut: Future<Output = Result<T, E>>,
+          T: Clone,
+          E: Clone,
+{
+    /// Creates a new group which keeps successful outputs cached once their
+    /// future completes, and evicts keys whose future failed.
+    ///
+    /// Cached outputs are evicted with [`forget`](SingleFlight::forget).
+    pub fn cache_ok() -> SingleFlight<K, Fut> {
+        SingleFlight {
+            flights: Mutex::new(HashMap::new()),
+            keep: Result::is_ok,
+        }
+    }
+}
+
+impl<K, Fut> Default for SingleFlight<K, Fut>
+    where K: Eq + Hash + Clone,
+          Fut: Future,
+          Fut::Output: Clone,
+{
+    fn default() -> SingleFlight<K, Fut> {
+        SingleFlight::new()
+    }
+}
+
+impl<K: fmt::Debug, Fut: Future> fmt::Debug for SingleFlight<K, Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flights = self.flights.lock().unwrap();
+        f.debug_struct("SingleFlight")
+            .field("keys", &flights.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// A future which resolves to the output of the future in flight for a key
+/// of a [`SingleFlight`](SingleFlight) group.
+///
+/// This value is created by the [`call`](SingleFlight::call) method.
+#[must_use = "futures do nothing unless polled"]
+pub struct SingleFlightCall<'a, K: 'a, Fut: Future + 'a>
+    where K: Eq + Hash + Clone,
+          Fut::Output: Clone,
+{
+    group: &'a SingleFlight<K, Fut>,
+    // `None` once the output has been returned
+    key: Option<K>,
+    shared: Shared<Fut>,
+}
+
+// The future itself is polled behind the `Arc` of `Shared`
+impl<'a, K, Fut> Unpin for SingleFlightCall<'a, K, Fut>
+    where K: Eq + Hash + Clone,
+          Fut: Future,
+          Fut::Output: Clone,
+{}
+
+impl<'a, K, Fut> fmt::Debug for SingleFlightCall<'a, K, Fut>
+    where K: Eq + Hash + Clone + fmt::Debug,
+          Fut: Future,
+          Fut::Output: Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SingleFlightCall")
+            .field("key", &self.key)
+            .field("shared", &self.shared)
+            .finish()
+    }
+}
+
+impl<'a, K, Fut> Future for SingleFlightCall<'a, K, Fut>
+    where K: Eq + Hash + Clone,
+          Fut: Future,
+          Fut::Output: Clone,
+{
+    type Output = Fut::Output;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Fut::Output> {
+        let this = &mut *self;
+        let output = match this.shared.poll_unpin(cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let key = this.key.take().expect("polled SingleFlightCall after completion");
+        this.group.complete(&key, &this.shared, &output);
+        Poll::Ready(output)
+    }
+}
+
+impl<'a, K, Fut> Drop for SingleFlightCall<'a, K, Fut>
+    where K: Eq + Hash + Clone,
+          Fut: Future,
+          Fut::Output: Clone,
+{
+    fn drop(&mut self) {
+        if let Some(key) = &self.key {
+            self.group.cancel(key, &self.shared);
+        }
+    }
+}
@@ -89,8 +89,5 @@ if_std! {
     pub mod io;
     #[doc(hidden)] pub use crate::io::{AsyncReadExt, AsyncWriteExt};
 
-    #[cfg(any(test, feature = "bench"))]
     pub mod lock;
-    #[cfg(not(any(test, feature = "bench")))]
-    mod lock;
 }
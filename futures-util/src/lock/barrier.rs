@@ -0,0 +1,180 @@
+use crate::task::WakerSet;
+use futures_core::future::Future;
+use futures_core::task::{self, Poll};
+use std::fmt;
+use std::marker::Unpin;
+use std::pin::PinMut;
+use std::sync::Mutex as StdMutex;
+
+/// A barrier letting a number of tasks wait for each other.
+///
+/// Each task calls [`wait`](Barrier::wait), and the returned futures resolve
+/// once the given number of tasks are waiting. The barrier can then be used
+/// again.
+pub struct Barrier {
+    num_tasks: usize,
+    state: StdMutex<State>,
+    // Tasks waiting in the current generation, all woken up when released
+    wakers: WakerSet,
+}
+
+struct State {
+    // Number of tasks waiting in the current generation
+    arrived: usize,
+
+    // Incremented each time the tasks are released
+    generation: usize,
+}
+
+impl Barrier {
+    /// Creates a new barrier which releases tasks in groups of `n`.
+    ///
+    /// A barrier created with `n == 0` behaves like one created with `n == 1`:
+    /// tasks don't wait at all.
+    pub fn new(n: usize) -> Barrier {
+        Barrier {
+            num_tasks: n,
+            state: StdMutex::new(State {
+                arrived: 0,
+                generation: 0,
+            }),
+            wakers: WakerSet::new(),
+        }
+    }
+
+    /// Waits until `n` tasks are waiting on this barrier, returning a future
+    /// which resolves once they are.
+    ///
+    /// The task starts waiting when the future is first polled. Dropping the
+    /// future before the tasks are released makes it stop waiting.
+    ///
+    /// The future of the task completing the group, chosen arbitrarily,
+    /// resolves to a result for which
+    /// [`is_leader`](BarrierWaitResult::is_leader) returns `true`.
+    pub fn wait(&self) -> BarrierWaitFuture<'_> {
+        BarrierWaitFuture {
+            barrier: Some(self),
+            generation: None,
+            wait_key: None,
+        }
+    }
+
+    fn cancel_wait(&self, generation: usize, wait_key: usize) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+
+        // Nothing to undo if the task has already been released
+        if state.generation == generation {
+            state.arrived -= 1;
+        }
+        self.wakers.remove(wait_key);
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("Barrier")
+            .field("num_tasks", &self.num_tasks)
+            .field("arrived", &state.arrived)
+            .finish()
+    }
+}
+
+/// A future which resolves when enough tasks are waiting on the target
+/// [`Barrier`](Barrier).
+///
+/// This value is created by the [`wait`](Barrier::wait) method.
+#[must_use = "futures do nothing unless polled"]
+pub struct BarrierWaitFuture<'a> {
+    // `None` once the tasks have been released
+    barrier: Option<&'a Barrier>,
+    // The generation the task waits in, once it started waiting
+    generation: Option<usize>,
+    wait_key: Option<usize>,
+}
+
+// Pinning is never projected to fields
+impl<'a> Unpin for BarrierWaitFuture<'a> {}
+
+impl<'a> fmt::Debug for BarrierWaitFuture<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BarrierWaitFuture")
+            .field("was_released", &self.barrier.is_none())
+            .field("barrier", &self.barrier)
+            .field("generation", &self.generation)
+            .field("wait_key", &self.wait_key)
+            .finish()
+    }
+}
+
+impl<'a> Future for BarrierWaitFuture<'a> {
+    type Output = BarrierWaitResult;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let barrier = this.barrier.expect("polled BarrierWaitFuture after completion");
+        let mut state = barrier.state.lock().unwrap();
+
+        match this.generation {
+            Some(generation) => {
+                if state.generation != generation {
+                    this.barrier = None;
+                    if let Some(wait_key) = this.wait_key.take() {
+                        barrier.wakers.remove(wait_key);
+                    }
+                    return Poll::Ready(BarrierWaitResult { is_leader: false });
+                }
+
+                // A wakeup from the release of a previous generation makes
+                // the task wait again
+                barrier.wakers.register(&mut this.wait_key, cx.waker());
+                Poll::Pending
+            }
+            None => {
+                state.arrived += 1;
+                if state.arrived < barrier.num_tasks {
+                    barrier.wakers.register(&mut this.wait_key, cx.waker());
+                    this.generation = Some(state.generation);
+                    return Poll::Pending;
+                }
+
+                state.arrived = 0;
+                state.generation = state.generation.wrapping_add(1);
+                drop(state);
+
+                this.barrier = None;
+                barrier.wakers.wake_all();
+                Poll::Ready(BarrierWaitResult { is_leader: true })
+            }
+        }
+    }
+}
+
+impl<'a> Drop for BarrierWaitFuture<'a> {
+    fn drop(&mut self) {
+        if let (Some(barrier), Some(generation), Some(wait_key)) =
+            (self.barrier, self.generation, self.wait_key)
+        {
+            barrier.cancel_wait(generation, wait_key);
+        }
+    }
+}
+
+/// The result of waiting on a [`Barrier`](Barrier).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult {
+    is_leader: bool,
+}
+
+impl BarrierWaitResult {
+    /// Returns whether this task completed the group of tasks released by the
+    /// barrier.
+    ///
+    /// Exactly one task of each group is the leader.
+    pub fn is_leader(&self) -> bool {
+        self.is_leader
+    }
+}
@@ -1,4 +1,3 @@
-//! Futures-powered synchronization primitives.
 #![allow(unused)]
 
 use futures_core::future::Future;
@@ -0,0 +1,164 @@
+use futures_core::future::Future;
+use futures_core::task::{self, Poll};
+use std::fmt;
+use std::marker::Unpin;
+use std::mem;
+use std::pin::PinMut;
+
+use crate::task::WakerSet;
+use super::{Mutex, MutexGuard, MutexLockFuture};
+
+/// A futures-aware condition variable.
+///
+/// This works with the futures-aware [`Mutex`](super::Mutex): waiting on the
+/// condition variable with [`wait`](Condvar::wait) unlocks the mutex, and
+/// returns a future which resolves to a guard once the task has been notified
+/// and the mutex locked again.
+///
+/// The task starts waiting before the mutex is unlocked, so a notification
+/// sent by a task which changed the protected data while holding the mutex
+/// isn't lost. Like with `std::sync::Condvar`, the condition should still be
+/// checked in a loop, as another task may have changed the data again by the
+/// time the mutex is locked.
+pub struct Condvar {
+    waiters: WakerSet,
+}
+
+impl Condvar {
+    /// Creates a new condition variable with no waiting task.
+    pub fn new() -> Condvar {
+        Condvar {
+            waiters: WakerSet::new(),
+        }
+    }
+
+    /// Unlocks the mutex locked by `guard` and waits for a notification,
+    /// returning a future which resolves to a new guard once the mutex has
+    /// been locked again.
+    ///
+    /// The mutex is unlocked when the future is first polled. Dropping the
+    /// future after that gives up on the notification, passing it on to the
+    /// next waiting task if it had already been received.
+    pub fn wait<'a, T: ?Sized>(&'a self, guard: MutexGuard<'a, T>) -> CondvarWaitFuture<'a, T> {
+        CondvarWaitFuture {
+            condvar: self,
+            state: WaitState::Unlocking(guard),
+        }
+    }
+
+    /// Notifies the task which has been waiting the longest.
+    ///
+    /// The notification is lost if no task is waiting.
+    pub fn notify_one(&self) {
+        self.waiters.wake_one();
+    }
+
+    /// Notifies all the tasks currently waiting.
+    pub fn notify_all(&self) {
+        self.waiters.wake_all();
+    }
+
+    fn cancel_wait(&self, wait_key: usize) {
+        // A notification received but never observed is passed on
+        if self.waiters.remove(wait_key) {
+            self.waiters.wake_one();
+        }
+    }
+}
+
+impl fmt::Debug for Condvar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Condvar")
+            .field("waiters", &self.waiters.len())
+            .finish()
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Condvar {
+        Condvar::new()
+    }
+}
+
+/// A future which resolves when the task has been notified through the target
+/// [`Condvar`](Condvar) and the mutex has been locked again.
+///
+/// This value is created by the [`wait`](Condvar::wait) method.
+#[must_use = "futures do nothing unless polled"]
+pub struct CondvarWaitFuture<'a, T: ?Sized + 'a> {
+    condvar: &'a Condvar,
+    state: WaitState<'a, T>,
+}
+
+enum WaitState<'a, T: ?Sized + 'a> {
+    // The mutex is still locked
+    Unlocking(MutexGuard<'a, T>),
+    // Waiting for a notification with the given key
+    Waiting(&'a Mutex<T>, usize),
+    Locking(MutexLockFuture<'a, T>),
+    Done,
+}
+
+// Pinning is never projected to fields
+impl<'a, T: ?Sized> Unpin for CondvarWaitFuture<'a, T> {}
+
+impl<'a, T: ?Sized> fmt::Debug for CondvarWaitFuture<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = match self.state {
+            WaitState::Unlocking(_) => "Unlocking",
+            WaitState::Waiting(..) => "Waiting",
+            WaitState::Locking(_) => "Locking",
+            WaitState::Done => "Done",
+        };
+        f.debug_struct("CondvarWaitFuture")
+            .field("condvar", &self.condvar)
+            .field("state", &state)
+            .finish()
+    }
+}
+
+impl<'a, T: ?Sized> Future for CondvarWaitFuture<'a, T> {
+    type Output = MutexGuard<'a, T>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        loop {
+            match mem::replace(&mut this.state, WaitState::Done) {
+                WaitState::Unlocking(guard) => {
+                    // Start waiting before unlocking, so that no notification
+                    // is missed in between.
+                    let mut wait_key = None;
+                    this.condvar.waiters.register(&mut wait_key, cx.waker());
+                    let mutex = guard.mutex();
+                    drop(guard);
+                    this.state = WaitState::Waiting(mutex, wait_key.unwrap());
+                }
+                WaitState::Waiting(mutex, wait_key) => {
+                    let mut key = Some(wait_key);
+                    if this.condvar.waiters.poll_wakeup(&mut key, cx.waker()).is_pending() {
+                        this.state = WaitState::Waiting(mutex, wait_key);
+                        return Poll::Pending;
+                    }
+                    this.state = WaitState::Locking(mutex.lock());
+                }
+                WaitState::Locking(mut lock) => {
+                    if let Poll::Ready(guard) = PinMut::new(&mut lock).poll(cx) {
+                        return Poll::Ready(guard);
+                    }
+                    this.state = WaitState::Locking(lock);
+                    return Poll::Pending;
+                }
+                WaitState::Done => panic!("polled CondvarWaitFuture after completion"),
+            }
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for CondvarWaitFuture<'a, T> {
+    fn drop(&mut self) {
+        if let WaitState::Waiting(_, wait_key) = self.state {
+            self.condvar.cancel_wait(wait_key);
+        }
+    }
+}
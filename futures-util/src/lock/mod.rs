@@ -0,0 +1,44 @@
+//! Futures-powered synchronization primitives.
+//!
+//! Unlike their counterparts in `std::sync`, waiting on these primitives
+//! doesn't block the thread: the current task is notified once the primitive
+//! becomes available instead. Guards can be held across suspension points.
+
+mod bilock;
+#[cfg(any(test, feature = "bench"))]
+pub use self::bilock::{BiLock, BiLockAcquire, BiLockGuard, ReuniteError};
+#[cfg(not(any(test, feature = "bench")))]
+pub(crate) use self::bilock::BiLock;
+
+mod mutex;
+pub use self::mutex::{
+    Mutex, MutexGuard, MutexLockFuture, OwnedMutexGuard, OwnedMutexLockFuture,
+};
+
+mod rwlock;
+pub use self::rwlock::{
+    RwLock, RwLockReadFuture, RwLockReadGuard, RwLockUpgradableReadFuture,
+    RwLockUpgradableReadGuard, RwLockUpgradeFuture, RwLockWriteFuture,
+    RwLockWriteGuard,
+};
+
+mod semaphore;
+pub use self::semaphore::{Semaphore, SemaphoreAcquireFuture, SemaphorePermit};
+
+mod notify;
+pub use self::notify::{Notify, NotifiedFuture};
+
+mod barrier;
+pub use self::barrier::{Barrier, BarrierWaitFuture, BarrierWaitResult};
+
+mod condvar;
+pub use self::condvar::{Condvar, CondvarWaitFuture};
+
+mod once_cell;
+pub use self::once_cell::{GetOrInitFuture, GetOrTryInitFuture, OnceCell};
+
+mod multilock;
+pub use self::multilock::{
+    MultiLock, MultiLockAcquire, MultiLockAcquireOwned, MultiLockGuard,
+    OwnedMultiLockGuard, ReuniteAllError,
+};
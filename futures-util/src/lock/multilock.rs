@@ -0,0 +1,269 @@
+use futures_core::future::Future;
+use futures_core::task::{self, Poll};
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::marker::Unpin;
+use std::ops::{Deref, DerefMut};
+use std::pin::PinMut;
+use std::sync::Arc;
+use std::vec::Vec;
+
+use super::{Mutex, MutexGuard, MutexLockFuture, OwnedMutexGuard, OwnedMutexLockFuture};
+
+/// A futures-aware lock shared between any number of owners.
+///
+/// This generalizes `BiLock` to more than two owners: the protected value is
+/// split into `n` handles, which can be cloned and sent to separate tasks.
+/// The handles can then be reunited to get the value back, once all of them
+/// are returned.
+///
+/// Tasks acquire the lock in the order they started waiting for it. Besides
+/// guards borrowing the handle with [`lock`](MultiLock::lock), the lock can
+/// be acquired with [`lock_owned`](MultiLock::lock_owned), whose guard is
+/// `'static` and can be kept in a struct across suspension points.
+///
+/// Like with `BiLock`, the data behind the lock is considered to be pinned:
+/// the locked value is only available through `PinMut` (not `&mut`) unless
+/// `T` is `Unpin`, and reuniting the handles is only possible when `T` is
+/// `Unpin`.
+pub struct MultiLock<T> {
+    mutex: Arc<Mutex<T>>,
+}
+
+impl<T> MultiLock<T> {
+    /// Creates a new lock protecting the provided data, returning `n` handles
+    /// to it.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `n` is zero.
+    pub fn new(t: T, n: usize) -> Vec<MultiLock<T>> {
+        assert!(n > 0, "a MultiLock needs at least one handle");
+
+        let mutex = Arc::new(Mutex::new(t));
+        let mut handles = (1..n)
+            .map(|_| MultiLock { mutex: mutex.clone() })
+            .collect::<Vec<_>>();
+        handles.push(MultiLock { mutex });
+        handles
+    }
+
+    /// Attempts to acquire the lock immediately.
+    ///
+    /// Returns `None` if the lock is currently held, or has been handed over
+    /// to a waiting task.
+    pub fn try_lock(&self) -> Option<MultiLockGuard<'_, T>> {
+        self.mutex.try_lock().map(|guard| MultiLockGuard { guard })
+    }
+
+    /// Acquires the lock, returning a future which resolves to a guard
+    /// borrowing this handle once the lock has been acquired.
+    pub fn lock(&self) -> MultiLockAcquire<'_, T> {
+        MultiLockAcquire {
+            future: self.mutex.lock(),
+        }
+    }
+
+    /// Acquires the lock, returning a future which resolves to a guard
+    /// holding a reference count to the lock rather than borrowing this
+    /// handle.
+    ///
+    /// The guard keeps the lock alive: the handles can't be reunited while it
+    /// exists.
+    pub fn lock_owned(&self) -> MultiLockAcquireOwned<T> {
+        MultiLockAcquireOwned {
+            future: self.mutex.clone().lock_owned(),
+        }
+    }
+
+    /// Returns `true` if both handles protect the same value.
+    pub fn is_same_lock(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.mutex, &other.mutex)
+    }
+
+    /// Attempts to put all the handles to a lock back together and recover
+    /// the original value.
+    ///
+    /// Succeeds only if the handles all originated from the same call to
+    /// [`MultiLock::new`](MultiLock::new), and no other handle nor owned guard
+    /// for that lock exists anymore.
+    pub fn reunite_all(handles: Vec<MultiLock<T>>) -> Result<T, ReuniteAllError<T>>
+    where
+        T: Unpin,
+    {
+        let is_whole = match handles.first() {
+            Some(first) => {
+                handles.iter().all(|handle| handle.is_same_lock(first))
+                    && Arc::strong_count(&first.mutex) == handles.len()
+            }
+            None => false,
+        };
+        if !is_whole {
+            return Err(ReuniteAllError(handles));
+        }
+
+        let mutex = handles.into_iter()
+            .map(|handle| handle.mutex)
+            .last()
+            .unwrap();
+        let mutex = Arc::try_unwrap(mutex)
+            .ok()
+            .expect("futures: try_unwrap failed in MultiLock<T>::reunite_all");
+        Ok(mutex.into_inner())
+    }
+}
+
+impl<T> Clone for MultiLock<T> {
+    fn clone(&self) -> Self {
+        MultiLock { mutex: self.mutex.clone() }
+    }
+}
+
+impl<T> fmt::Debug for MultiLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MultiLock")
+            .field("mutex", &self.mutex)
+            .finish()
+    }
+}
+
+/// Error indicating the `MultiLock<T>`s given to
+/// [`reunite_all`](MultiLock::reunite_all) weren't all the handles of a single
+/// lock.
+pub struct ReuniteAllError<T>(pub Vec<MultiLock<T>>);
+
+impl<T> fmt::Debug for ReuniteAllError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("ReuniteAllError")
+            .field(&"...")
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for ReuniteAllError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "tried to reunite MultiLocks that don't form a whole")
+    }
+}
+
+impl<T: Any> Error for ReuniteAllError<T> {
+    fn description(&self) -> &str {
+        "tried to reunite MultiLocks that don't form a whole"
+    }
+}
+
+/// Returned RAII guard from the `lock` and `try_lock` methods.
+///
+/// This structure acts as a sentinel to the data in the `MultiLock<T>` itself,
+/// implementing `Deref` to `T`, and `DerefMut` if `T` is `Unpin`. When
+/// dropped, the lock will be unlocked.
+pub struct MultiLockGuard<'a, T: 'a> {
+    guard: MutexGuard<'a, T>,
+}
+
+/// Returned RAII guard from the `lock_owned` method.
+///
+/// This is identical to [`MultiLockGuard`](MultiLockGuard), except that it
+/// holds a reference count to the lock rather than borrowing a handle.
+pub struct OwnedMultiLockGuard<T> {
+    guard: OwnedMutexGuard<T>,
+}
+
+impl<'a, T> MultiLockGuard<'a, T> {
+    /// Get a mutable pinned reference to the locked value.
+    pub fn as_pin_mut(&mut self) -> PinMut<'_, T> {
+        // Safety: we never allow moving a !Unpin value out of a multilock, nor
+        // allow mutable access to it
+        unsafe { PinMut::new_unchecked(&mut *self.guard) }
+    }
+}
+
+impl<T> OwnedMultiLockGuard<T> {
+    /// Get a mutable pinned reference to the locked value.
+    pub fn as_pin_mut(&mut self) -> PinMut<'_, T> {
+        // Safety: we never allow moving a !Unpin value out of a multilock, nor
+        // allow mutable access to it
+        unsafe { PinMut::new_unchecked(&mut *self.guard) }
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for MultiLockGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MultiLockGuard")
+            .field("value", &&**self)
+            .finish()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OwnedMultiLockGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OwnedMultiLockGuard")
+            .field("value", &&**self)
+            .finish()
+    }
+}
+
+impl<'a, T> Deref for MultiLockGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &*self.guard
+    }
+}
+
+impl<'a, T: Unpin> DerefMut for MultiLockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut *self.guard
+    }
+}
+
+impl<T> Deref for OwnedMultiLockGuard<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &*self.guard
+    }
+}
+
+impl<T: Unpin> DerefMut for OwnedMultiLockGuard<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut *self.guard
+    }
+}
+
+/// Future returned by `MultiLock::lock` which will resolve when the lock is
+/// acquired.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct MultiLockAcquire<'a, T: 'a> {
+    future: MutexLockFuture<'a, T>,
+}
+
+// Pinning is never projected to fields
+impl<'a, T> Unpin for MultiLockAcquire<'a, T> {}
+
+impl<'a, T> Future for MultiLockAcquire<'a, T> {
+    type Output = MultiLockGuard<'a, T>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        PinMut::new(&mut self.future).poll(cx).map(|guard| MultiLockGuard { guard })
+    }
+}
+
+/// Future returned by `MultiLock::lock_owned` which will resolve when the lock
+/// is acquired.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct MultiLockAcquireOwned<T> {
+    future: OwnedMutexLockFuture<T>,
+}
+
+// Pinning is never projected to fields
+impl<T> Unpin for MultiLockAcquireOwned<T> {}
+
+impl<T> Future for MultiLockAcquireOwned<T> {
+    type Output = OwnedMultiLockGuard<T>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        PinMut::new(&mut self.future).poll(cx).map(|guard| OwnedMultiLockGuard { guard })
+    }
+}
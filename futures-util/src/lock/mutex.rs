@@ -0,0 +1,426 @@
+use futures_core::future::Future;
+use futures_core::task::{self, Poll, Waker};
+use slab::Slab;
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::Unpin;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::pin::PinMut;
+use std::sync::{Arc, Mutex as StdMutex};
+
+/// A futures-aware mutex.
+///
+/// Locking the mutex with [`lock`](Mutex::lock) returns a future which
+/// resolves to a guard once the mutex has been acquired, so the current task
+/// is notified instead of blocking the thread while the mutex is held
+/// elsewhere. The guard can be held across suspension points such as
+/// `await!`.
+///
+/// The mutex is fair: tasks acquire it in the order they started waiting for
+/// it, so a waiting task can't be starved by others repeatedly locking the
+/// mutex. When the mutex is unlocked with tasks waiting, it is handed over to
+/// the task which has been waiting the longest.
+///
+/// To hold the lock without borrowing the mutex, for example in a spawned
+/// task, put the mutex in an `Arc` and use
+/// [`lock_owned`](Mutex::lock_owned).
+pub struct Mutex<T: ?Sized> {
+    state: StdMutex<State>,
+    value: UnsafeCell<T>,
+}
+
+struct State {
+    // `true` while the mutex is held, including when it has been handed over
+    // to a waiter which hasn't been polled yet.
+    is_locked: bool,
+
+    waiters: Slab<Waiter>,
+
+    // Keys of the waiters in `waiters` which are still waiting, in the order
+    // they started waiting.
+    queue: VecDeque<usize>,
+}
+
+enum Waiter {
+    // The task to notify once the mutex is handed over to this waiter.
+    Waiting(Waker),
+
+    // The mutex has been handed over to this waiter.
+    Acquired,
+}
+
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    /// Creates a new futures-aware mutex in an unlocked state.
+    pub fn new(t: T) -> Mutex<T> {
+        Mutex {
+            state: StdMutex::new(State {
+                is_locked: false,
+                waiters: Slab::new(),
+                queue: VecDeque::new(),
+            }),
+            value: UnsafeCell::new(t),
+        }
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Attempts to acquire the mutex immediately.
+    ///
+    /// Returns `None` if the mutex is currently held, or has been handed over
+    /// to a waiting task.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        if self.try_acquire() {
+            Some(MutexGuard { mutex: self })
+        } else {
+            None
+        }
+    }
+
+    /// Acquires the mutex, returning a future which resolves to a guard once
+    /// the mutex has been acquired.
+    ///
+    /// Dropping the future before it completes gives up on the mutex. If the
+    /// mutex had already been handed over to it, the mutex is passed on to the
+    /// next task waiting for it.
+    pub fn lock(&self) -> MutexLockFuture<'_, T> {
+        MutexLockFuture {
+            mutex: Some(self),
+            wait_key: None,
+        }
+    }
+
+    /// Acquires the mutex through an `Arc`, returning a future which resolves
+    /// to a guard holding a reference count to the mutex rather than a
+    /// borrow.
+    ///
+    /// This is otherwise identical to [`lock`](Mutex::lock).
+    pub fn lock_owned(self: Arc<Self>) -> OwnedMutexLockFuture<T> {
+        OwnedMutexLockFuture {
+            mutex: Some(self),
+            wait_key: None,
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `Mutex` mutably, no actual locking needs to
+    /// take place: the mutable borrow statically guarantees no locks exist.
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.value.get() }
+    }
+
+    fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.is_locked {
+            false
+        } else {
+            state.is_locked = true;
+            true
+        }
+    }
+
+    // Polls for the mutex on behalf of a lock future, whose position in the
+    // queue of waiters is tracked by `wait_key`.
+    fn poll_acquire(&self, wait_key: &mut Option<usize>, cx: &mut task::Context) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+
+        let key = match *wait_key {
+            Some(key) => key,
+            None => {
+                if !state.is_locked {
+                    state.is_locked = true;
+                    return Poll::Ready(());
+                }
+
+                let key = state.waiters.insert(Waiter::Waiting(cx.waker().clone()));
+                state.queue.push_back(key);
+                *wait_key = Some(key);
+                return Poll::Pending;
+            }
+        };
+
+        match &mut state.waiters[key] {
+            Waiter::Acquired => {}
+            Waiter::Waiting(waker) => {
+                if !waker.will_wake(cx.waker()) {
+                    *waker = cx.waker().clone();
+                }
+                return Poll::Pending;
+            }
+        }
+
+        state.waiters.remove(key);
+        *wait_key = None;
+        Poll::Ready(())
+    }
+
+    // Gives up on the mutex on behalf of a lock future which is dropped while
+    // waiting.
+    fn cancel_acquire(&self, wait_key: usize) {
+        let waker = {
+            let mut state = match self.state.lock() {
+                Ok(state) => state,
+                Err(_) => return,
+            };
+
+            match state.waiters.remove(wait_key) {
+                // The mutex was handed over to the future but it was never
+                // polled, pass the mutex on to the next waiter.
+                Waiter::Acquired => state.release(),
+                Waiter::Waiting(_) => {
+                    state.queue.retain(|key| *key != wait_key);
+                    None
+                }
+            }
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn unlock(&self) {
+        let waker = self.state.lock().unwrap().release();
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl State {
+    // Releases the mutex, handing it over to the first waiter if there is
+    // one. The mutex stays locked in that case so that no other task can barge
+    // in before the waiter is polled. Returns the task to notify.
+    fn release(&mut self) -> Option<Waker> {
+        match self.queue.pop_front() {
+            Some(key) => {
+                match mem::replace(&mut self.waiters[key], Waiter::Acquired) {
+                    Waiter::Waiting(waker) => Some(waker),
+                    Waiter::Acquired => unreachable!("mutex handed over twice"),
+                }
+            }
+            None => {
+                self.is_locked = false;
+                None
+            }
+        }
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("Mutex")
+            .field("is_locked", &state.is_locked)
+            .field("waiters", &state.queue.len())
+            .finish()
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Mutex<T> {
+        Mutex::new(T::default())
+    }
+}
+
+impl<T> From<T> for Mutex<T> {
+    fn from(t: T) -> Mutex<T> {
+        Mutex::new(t)
+    }
+}
+
+/// A future which resolves when the target mutex has been successfully
+/// acquired.
+///
+/// This value is created by the [`lock`](Mutex::lock) method.
+#[must_use = "futures do nothing unless polled"]
+pub struct MutexLockFuture<'a, T: ?Sized + 'a> {
+    // `None` once the guard has been returned
+    mutex: Option<&'a Mutex<T>>,
+    wait_key: Option<usize>,
+}
+
+// Pinning is never projected to fields
+impl<'a, T: ?Sized> Unpin for MutexLockFuture<'a, T> {}
+
+impl<'a, T: ?Sized> fmt::Debug for MutexLockFuture<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MutexLockFuture")
+            .field("was_acquired", &self.mutex.is_none())
+            .field("mutex", &self.mutex)
+            .field("wait_key", &self.wait_key)
+            .finish()
+    }
+}
+
+impl<'a, T: ?Sized> Future for MutexLockFuture<'a, T> {
+    type Output = MutexGuard<'a, T>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mutex = this.mutex.expect("polled MutexLockFuture after completion");
+
+        if mutex.poll_acquire(&mut this.wait_key, cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        this.mutex = None;
+        Poll::Ready(MutexGuard { mutex })
+    }
+}
+
+impl<'a, T: ?Sized> Drop for MutexLockFuture<'a, T> {
+    fn drop(&mut self) {
+        if let (Some(mutex), Some(wait_key)) = (self.mutex, self.wait_key) {
+            mutex.cancel_acquire(wait_key);
+        }
+    }
+}
+
+/// A future which resolves when the target mutex has been successfully
+/// acquired, holding a reference count to the mutex.
+///
+/// This value is created by the [`lock_owned`](Mutex::lock_owned) method.
+#[must_use = "futures do nothing unless polled"]
+pub struct OwnedMutexLockFuture<T: ?Sized> {
+    // `None` once the guard has been returned
+    mutex: Option<Arc<Mutex<T>>>,
+    wait_key: Option<usize>,
+}
+
+// Pinning is never projected to fields
+impl<T: ?Sized> Unpin for OwnedMutexLockFuture<T> {}
+
+impl<T: ?Sized> fmt::Debug for OwnedMutexLockFuture<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OwnedMutexLockFuture")
+            .field("was_acquired", &self.mutex.is_none())
+            .field("mutex", &self.mutex)
+            .field("wait_key", &self.wait_key)
+            .finish()
+    }
+}
+
+impl<T: ?Sized> Future for OwnedMutexLockFuture<T> {
+    type Output = OwnedMutexGuard<T>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        {
+            let mutex = this.mutex.as_ref().expect("polled OwnedMutexLockFuture after completion");
+            if mutex.poll_acquire(&mut this.wait_key, cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+
+        Poll::Ready(OwnedMutexGuard { mutex: this.mutex.take().unwrap() })
+    }
+}
+
+impl<T: ?Sized> Drop for OwnedMutexLockFuture<T> {
+    fn drop(&mut self) {
+        if let (Some(mutex), Some(wait_key)) = (&self.mutex, self.wait_key) {
+            mutex.cancel_acquire(wait_key);
+        }
+    }
+}
+
+/// An RAII guard returned by the `lock` and `try_lock` methods.
+///
+/// When this structure is dropped (falls out of scope), the lock will be
+/// unlocked, handing it over to the next task waiting for it if there is
+/// one.
+pub struct MutexGuard<'a, T: ?Sized + 'a> {
+    mutex: &'a Mutex<T>,
+}
+
+// Sharing the guard shares the data
+unsafe impl<'a, T: ?Sized + Sync> Sync for MutexGuard<'a, T> {}
+
+impl<'a, T: ?Sized> MutexGuard<'a, T> {
+    // The mutex this guard locks, used to lock it again after unlocking it
+    pub(super) fn mutex(&self) -> &'a Mutex<T> {
+        self.mutex
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MutexGuard")
+            .field("value", &&**self)
+            .field("mutex", &self.mutex)
+            .finish()
+    }
+}
+
+impl<'a, T: ?Sized> Deref for MutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+/// An RAII guard returned by the `lock_owned` method.
+///
+/// This behaves like a [`MutexGuard`](MutexGuard), except that it keeps the
+/// mutex alive through an `Arc` instead of borrowing it.
+pub struct OwnedMutexGuard<T: ?Sized> {
+    mutex: Arc<Mutex<T>>,
+}
+
+// Sharing the guard shares the data
+unsafe impl<T: ?Sized + Sync> Sync for OwnedMutexGuard<T> {}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for OwnedMutexGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OwnedMutexGuard")
+            .field("value", &&**self)
+            .field("mutex", &self.mutex)
+            .finish()
+    }
+}
+
+impl<T: ?Sized> Deref for OwnedMutexGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for OwnedMutexGuard<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for OwnedMutexGuard<T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
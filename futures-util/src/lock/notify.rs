@@ -0,0 +1,216 @@
+use crate::task::WakerSet;
+use futures_core::future::Future;
+use futures_core::task::{self, Poll};
+use std::fmt;
+use std::marker::Unpin;
+use std::mem;
+use std::pin::PinMut;
+use std::sync::Mutex as StdMutex;
+
+/// Notifies tasks waiting for an event.
+///
+/// Tasks wait for a notification with [`notified`](Notify::notified), and are
+/// woken up in the order they started waiting by
+/// [`notify_one`](Notify::notify_one) or all at once by
+/// [`notify_all`](Notify::notify_all).
+///
+/// If `notify_one` is called while no task is waiting, the notification is
+/// stored and the next task to wait completes right away, so a notification
+/// sent just before a task starts waiting isn't lost. At most one
+/// notification is stored at a time. A `notify_all` call is observed by all
+/// the futures returned by `notified` before it, even those which haven't
+/// been polled yet.
+pub struct Notify {
+    // The lock is held while handing out notifications, so that a
+    // notification is either stored or received by a waiter.
+    state: StdMutex<State>,
+    waiters: WakerSet,
+}
+
+struct State {
+    // `true` if `notify_one` was called with no task waiting
+    permit: bool,
+
+    // Incremented by each `notify_all` call
+    generation: usize,
+}
+
+impl Notify {
+    /// Creates a new `Notify` with no stored notification.
+    pub fn new() -> Notify {
+        Notify {
+            state: StdMutex::new(State {
+                permit: false,
+                generation: 0,
+            }),
+            waiters: WakerSet::new(),
+        }
+    }
+
+    /// Returns a future which resolves once this `Notify` is notified.
+    ///
+    /// The future starts waiting when it is first polled, but observes the
+    /// `notify_all` calls made since it was created: checking a condition
+    /// between creating the future and awaiting it doesn't miss them.
+    pub fn notified(&self) -> NotifiedFuture<'_> {
+        NotifiedFuture {
+            notify: Some(self),
+            generation: self.state.lock().unwrap().generation,
+            wait_key: None,
+        }
+    }
+
+    /// Notifies the task which has been waiting the longest.
+    ///
+    /// If no task is waiting, the notification is stored for the next task to
+    /// wait.
+    pub fn notify_one(&self) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            let waker = self.waiters.take_one();
+            if waker.is_none() {
+                state.permit = true;
+            }
+            waker
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Notifies all the tasks currently waiting, and those which will wait
+    /// on a future already returned by [`notified`](Notify::notified).
+    ///
+    /// The notification isn't stored for futures created afterwards.
+    pub fn notify_all(&self) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.generation = state.generation.wrapping_add(1);
+        }
+        self.waiters.wake_all();
+    }
+
+    // Polls for a notification on behalf of a future created during
+    // `generation`, whose position in the queue of waiters is tracked by
+    // `wait_key`.
+    fn poll_notified(
+        &self,
+        generation: usize,
+        wait_key: &mut Option<usize>,
+        cx: &mut task::Context,
+    ) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+
+        if wait_key.is_none() {
+            if state.generation != generation || mem::replace(&mut state.permit, false) {
+                return Poll::Ready(());
+            }
+        }
+
+        if self.waiters.poll_wakeup(wait_key, cx.waker()).is_ready() {
+            return Poll::Ready(());
+        }
+
+        // `notify_all` was called, but this future hasn't been woken up by
+        // it yet. Any wakeup received now comes from `notify_all` as well,
+        // since `notify_one` only hands out wakeups while holding the lock.
+        if state.generation != generation {
+            if let Some(key) = wait_key.take() {
+                self.waiters.remove(key);
+            }
+            return Poll::Ready(());
+        }
+
+        Poll::Pending
+    }
+
+    fn cancel_notified(&self, wait_key: usize) {
+        let waker = {
+            let mut state = match self.state.lock() {
+                Ok(state) => state,
+                Err(_) => return,
+            };
+
+            // A notification received by the future but never observed is
+            // passed on to the next waiter.
+            if !self.waiters.remove(wait_key) {
+                return;
+            }
+            let waker = self.waiters.take_one();
+            if waker.is_none() {
+                state.permit = true;
+            }
+            waker
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl fmt::Debug for Notify {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Notify")
+            .field("permit", &self.state.lock().unwrap().permit)
+            .field("waiters", &self.waiters.len())
+            .finish()
+    }
+}
+
+impl Default for Notify {
+    fn default() -> Notify {
+        Notify::new()
+    }
+}
+
+/// A future which resolves when the target [`Notify`](Notify) is notified.
+///
+/// This value is created by the [`notified`](Notify::notified) method.
+#[must_use = "futures do nothing unless polled"]
+pub struct NotifiedFuture<'a> {
+    // `None` once the notification has been received
+    notify: Option<&'a Notify>,
+    // The number of `notify_all` calls made before the future was created
+    generation: usize,
+    wait_key: Option<usize>,
+}
+
+// Pinning is never projected to fields
+impl<'a> Unpin for NotifiedFuture<'a> {}
+
+impl<'a> fmt::Debug for NotifiedFuture<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NotifiedFuture")
+            .field("was_notified", &self.notify.is_none())
+            .field("notify", &self.notify)
+            .field("generation", &self.generation)
+            .field("wait_key", &self.wait_key)
+            .finish()
+    }
+}
+
+impl<'a> Future for NotifiedFuture<'a> {
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<()> {
+        let this = &mut *self;
+        let notify = this.notify.expect("polled NotifiedFuture after completion");
+
+        if notify.poll_notified(this.generation, &mut this.wait_key, cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        this.notify = None;
+        Poll::Ready(())
+    }
+}
+
+impl<'a> Drop for NotifiedFuture<'a> {
+    fn drop(&mut self) {
+        if let (Some(notify), Some(wait_key)) = (self.notify, self.wait_key) {
+            notify.cancel_notified(wait_key);
+        }
+    }
+}
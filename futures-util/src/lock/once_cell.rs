@@ -0,0 +1,370 @@
+use futures_core::future::{Future, TryFuture};
+use futures_core::task::{self, Poll};
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::marker::Unpin;
+use std::pin::PinMut;
+use std::sync::Mutex as StdMutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::future::{FutureExt, UnitError};
+use crate::task::WakerSet;
+
+/// A cell which is written to at most once, by an asynchronous initializer.
+///
+/// The cell is typically initialized on first use with
+/// [`get_or_init`](OnceCell::get_or_init), which runs the given initializer
+/// only if the cell is empty. Tasks calling it while the initializer runs
+/// wait for its result instead of running their own.
+///
+/// If the initializer fails, as with
+/// [`get_or_try_init`](OnceCell::get_or_try_init), or if the future running it
+/// is dropped before it completes, the cell stays empty and one of the waiting
+/// tasks runs its own initializer instead.
+pub struct OnceCell<T> {
+    // `true` once `value` has been written
+    is_set: AtomicBool,
+    // `true` while a task runs its initializer
+    is_initializing: StdMutex<bool>,
+    // Tasks waiting for the initializer to complete
+    waiters: WakerSet,
+    value: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T: Send> Send for OnceCell<T> {}
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+
+impl<T> OnceCell<T> {
+    /// Creates a new empty cell.
+    pub fn new() -> OnceCell<T> {
+        OnceCell {
+            is_set: AtomicBool::new(false),
+            is_initializing: StdMutex::new(false),
+            waiters: WakerSet::new(),
+            value: UnsafeCell::new(None),
+        }
+    }
+
+    /// Returns a reference to the value, or `None` if the cell is empty.
+    pub fn get(&self) -> Option<&T> {
+        if self.is_set.load(Ordering::Acquire) {
+            unsafe { (*self.value.get()).as_ref() }
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the value, or `None` if the cell is
+    /// empty.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        unsafe { (*self.value.get()).as_mut() }
+    }
+
+    /// Sets the value of the cell.
+    ///
+    /// Returns the value back if the cell is already set, or if a task is
+    /// running its initializer.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        {
+            let mut is_initializing = self.is_initializing.lock().unwrap();
+            if *is_initializing || self.is_set.load(Ordering::Acquire) {
+                return Err(value);
+            }
+            *is_initializing = true;
+        }
+
+        self.complete(value);
+        Ok(())
+    }
+
+    /// Consumes this cell, returning the value if it was set.
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+
+    /// Returns a future which resolves to a reference to the value of the
+    /// cell, initializing it with the future returned by `f` if the cell is
+    /// empty.
+    ///
+    /// `f` is only called if no other task is running its initializer. If
+    /// one is, the returned future waits for its result, and only calls `f`
+    /// if that initializer fails or is cancelled.
+    pub fn get_or_init<F, Fut>(&self, f: F) -> GetOrInitFuture<'_, T, F, Fut>
+        where F: FnOnce() -> Fut,
+              Fut: Future<Output = T>,
+    {
+        GetOrInitFuture {
+            init: Some(f),
+            inner: Initialize::new(self),
+        }
+    }
+
+    /// Returns a future which resolves to a reference to the value of the
+    /// cell, initializing it with the fallible future returned by `f` if the
+    /// cell is empty.
+    ///
+    /// This is the fallible version of [`get_or_init`](OnceCell::get_or_init).
+    /// If the initializer fails, the error is returned and the cell stays
+    /// empty, letting the next task waiting on it retry.
+    pub fn get_or_try_init<F, Fut>(&self, f: F) -> GetOrTryInitFuture<'_, T, F, Fut>
+        where F: FnOnce() -> Fut,
+              Fut: TryFuture<Ok = T>,
+    {
+        GetOrTryInitFuture {
+            init: Some(f),
+            inner: Initialize::new(self),
+        }
+    }
+
+    // Polls for the right to initialize the cell, whose position in the queue
+    // of waiters is tracked by `wait_key`. Resolves to `true` if the cell has
+    // been set in the meantime.
+    fn poll_start(&self, wait_key: &mut Option<usize>, cx: &mut task::Context) -> Poll<bool> {
+        let mut is_initializing = self.is_initializing.lock().unwrap();
+
+        if let Some(key) = *wait_key {
+            if self.is_set.load(Ordering::Acquire) {
+                self.waiters.remove(key);
+                *wait_key = None;
+            } else if self.waiters.poll_wakeup(wait_key, cx.waker()).is_pending() {
+                return Poll::Pending;
+            }
+        }
+
+        if self.is_set.load(Ordering::Acquire) {
+            return Poll::Ready(true);
+        }
+
+        if !*is_initializing {
+            *is_initializing = true;
+            return Poll::Ready(false);
+        }
+
+        self.waiters.register(wait_key, cx.waker());
+        Poll::Pending
+    }
+
+    // Sets the value once the initializer completed, waking up all the
+    // waiters.
+    fn complete(&self, value: T) -> &T {
+        // The task running the initializer has exclusive access to the value
+        unsafe { *self.value.get() = Some(value) };
+        self.is_set.store(true, Ordering::Release);
+
+        *self.is_initializing.lock().unwrap() = false;
+        self.waiters.wake_all();
+
+        self.get().unwrap()
+    }
+
+    // Gives up on initializing the cell, letting the next waiter try.
+    fn abandon(&self) {
+        let waker = {
+            let mut is_initializing = match self.is_initializing.lock() {
+                Ok(is_initializing) => is_initializing,
+                Err(_) => return,
+            };
+            *is_initializing = false;
+            self.waiters.take_one()
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn cancel_wait(&self, wait_key: usize) {
+        let waker = {
+            let _is_initializing = match self.is_initializing.lock() {
+                Ok(is_initializing) => is_initializing,
+                Err(_) => return,
+            };
+
+            // A waiter notified to retry passes its turn on
+            if self.waiters.remove(wait_key) && !self.is_set.load(Ordering::Acquire) {
+                self.waiters.take_one()
+            } else {
+                None
+            }
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OnceCell")
+            .field("value", &self.get())
+            .finish()
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> OnceCell<T> {
+        OnceCell::new()
+    }
+}
+
+impl<T> From<T> for OnceCell<T> {
+    fn from(t: T) -> OnceCell<T> {
+        let cell = OnceCell::new();
+        unsafe { *cell.value.get() = Some(t) };
+        cell.is_set.store(true, Ordering::Relaxed);
+        cell
+    }
+}
+
+// The part of the `get_or_*` futures dealing with the cell, generic over the
+// initializer's future.
+struct Initialize<'a, T: 'a, Fut> {
+    cell: &'a OnceCell<T>,
+    // The initializer, while this future runs it
+    future: Option<Fut>,
+    wait_key: Option<usize>,
+}
+
+impl<'a, T, Fut> Initialize<'a, T, Fut>
+    where Fut: TryFuture<Ok = T>,
+{
+    fn new(cell: &'a OnceCell<T>) -> Initialize<'a, T, Fut> {
+        Initialize {
+            cell,
+            future: None,
+            wait_key: None,
+        }
+    }
+
+    // `start` creates the initializer if this future gets to run it
+    fn poll_init(
+        self: PinMut<Self>,
+        cx: &mut task::Context,
+        start: impl FnOnce() -> Fut,
+    ) -> Poll<Result<&'a T, Fut::Error>> {
+        // Safe to call `get_mut_unchecked` because we won't move the future.
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let cell = this.cell;
+        let mut start = Some(start);
+
+        loop {
+            if let Some(future) = &mut this.future {
+                let result = match unsafe { PinMut::new_unchecked(future) }.try_poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(result) => result,
+                };
+                this.future = None;
+
+                return Poll::Ready(match result {
+                    Ok(value) => Ok(cell.complete(value)),
+                    Err(e) => {
+                        cell.abandon();
+                        Err(e)
+                    }
+                });
+            }
+
+            match cell.poll_start(&mut this.wait_key, cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(true) => return Poll::Ready(Ok(cell.get().unwrap())),
+                Poll::Ready(false) => {
+                    let start = start.take().unwrap();
+                    this.future = Some(start());
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T, Fut> Drop for Initialize<'a, T, Fut> {
+    fn drop(&mut self) {
+        if self.future.is_some() {
+            self.cell.abandon();
+        } else if let Some(wait_key) = self.wait_key {
+            self.cell.cancel_wait(wait_key);
+        }
+    }
+}
+
+/// A future which resolves to a reference to the value of the target
+/// [`OnceCell`](OnceCell), once it has been initialized.
+///
+/// This value is created by the [`get_or_init`](OnceCell::get_or_init) method.
+#[must_use = "futures do nothing unless polled"]
+pub struct GetOrInitFuture<'a, T: 'a, F, Fut> {
+    init: Option<F>,
+    inner: Initialize<'a, T, UnitError<Fut>>,
+}
+
+impl<'a, T, F, Fut: Unpin> Unpin for GetOrInitFuture<'a, T, F, Fut> {}
+
+impl<'a, T, F, Fut> fmt::Debug for GetOrInitFuture<'a, T, F, Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GetOrInitFuture")
+            .field("is_initializing", &self.inner.future.is_some())
+            .field("wait_key", &self.inner.wait_key)
+            .finish()
+    }
+}
+
+impl<'a, T, F, Fut> Future for GetOrInitFuture<'a, T, F, Fut>
+    where F: FnOnce() -> Fut,
+          Fut: Future<Output = T>,
+{
+    type Output = &'a T;
+
+    fn poll(self: PinMut<Self>, cx: &mut task::Context) -> Poll<&'a T> {
+        // Safe to call `get_mut_unchecked` because we won't move `inner`.
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let init = &mut this.init;
+        let inner = unsafe { PinMut::new_unchecked(&mut this.inner) };
+
+        inner.poll_init(cx, || {
+            let f = init.take().expect("polled GetOrInitFuture after completion");
+            f().unit_error()
+        }).map(|result| result.unwrap())
+    }
+}
+
+/// A future which resolves to a reference to the value of the target
+/// [`OnceCell`](OnceCell), once it has been initialized, or to the error of a
+/// failed initializer.
+///
+/// This value is created by the
+/// [`get_or_try_init`](OnceCell::get_or_try_init) method.
+#[must_use = "futures do nothing unless polled"]
+pub struct GetOrTryInitFuture<'a, T: 'a, F, Fut> {
+    init: Option<F>,
+    inner: Initialize<'a, T, Fut>,
+}
+
+impl<'a, T, F, Fut: Unpin> Unpin for GetOrTryInitFuture<'a, T, F, Fut> {}
+
+impl<'a, T, F, Fut> fmt::Debug for GetOrTryInitFuture<'a, T, F, Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GetOrTryInitFuture")
+            .field("is_initializing", &self.inner.future.is_some())
+            .field("wait_key", &self.inner.wait_key)
+            .finish()
+    }
+}
+
+impl<'a, T, F, Fut> Future for GetOrTryInitFuture<'a, T, F, Fut>
+    where F: FnOnce() -> Fut,
+          Fut: TryFuture<Ok = T>,
+{
+    type Output = Result<&'a T, Fut::Error>;
+
+    fn poll(self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        // Safe to call `get_mut_unchecked` because we won't move `inner`.
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let init = &mut this.init;
+        let inner = unsafe { PinMut::new_unchecked(&mut this.inner) };
+
+        inner.poll_init(cx, || {
+            let f = init.take().expect("polled GetOrTryInitFuture after completion");
+            f()
+        })
+    }
+}
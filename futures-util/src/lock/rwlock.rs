@@ -0,0 +1,610 @@
+use futures_core::future::Future;
+use futures_core::task::{self, Poll, Waker};
+use slab::Slab;
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::Unpin;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::pin::PinMut;
+use std::sync::Mutex as StdMutex;
+use std::vec::Vec;
+
+/// A futures-aware reader-writer lock.
+///
+/// Any number of readers can hold the lock at once, while a writer has
+/// exclusive access. Acquiring the lock with [`read`](RwLock::read),
+/// [`write`](RwLock::write) or [`upgradable_read`](RwLock::upgradable_read)
+/// returns a future which resolves to a guard once the lock has been acquired,
+/// so the current task is notified instead of blocking the thread.
+///
+/// Tasks acquire the lock in the order they started waiting for it. In
+/// particular, once a writer is waiting, readers arriving after it wait too,
+/// so a steady flow of readers can't starve writers.
+///
+/// An upgradable read guard has shared access alongside plain readers, but
+/// excludes writers and other upgradable readers. It can be upgraded to a
+/// write guard without letting any writer in between, and the upgrade takes
+/// priority over all waiting tasks.
+pub struct RwLock<T: ?Sized> {
+    state: StdMutex<State>,
+    value: UnsafeCell<T>,
+}
+
+struct State {
+    // Number of readers holding the lock, including the upgradable reader
+    readers: usize,
+
+    // `true` while a writer holds the lock
+    writer: bool,
+
+    // `true` while an upgradable reader holds the lock
+    upgradable: bool,
+
+    waiters: Slab<Waiter>,
+
+    // Keys of the waiters in `waiters` which are still waiting, in the order
+    // they started waiting. A pending upgrade isn't part of the queue.
+    queue: VecDeque<usize>,
+
+    // Key of the pending upgrade of the upgradable reader, if any
+    upgrade: Option<usize>,
+}
+
+struct Waiter {
+    kind: Kind,
+
+    // The task to notify once the lock is handed over to this waiter, `None`
+    // once it has been.
+    waker: Option<Waker>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Read,
+    UpgradableRead,
+    Write,
+    // Turns the upgradable read lock into a write lock
+    Upgrade,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    /// Creates a new futures-aware reader-writer lock in an unlocked state.
+    pub fn new(t: T) -> RwLock<T> {
+        RwLock {
+            state: StdMutex::new(State {
+                readers: 0,
+                writer: false,
+                upgradable: false,
+                waiters: Slab::new(),
+                queue: VecDeque::new(),
+                upgrade: None,
+            }),
+            value: UnsafeCell::new(t),
+        }
+    }
+
+    /// Consumes this lock, returning the underlying data.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Attempts to acquire the lock with shared read access immediately.
+    ///
+    /// Returns `None` if a writer holds the lock, or if tasks are waiting to
+    /// acquire it.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        if self.try_acquire(Kind::Read) {
+            Some(RwLockReadGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to acquire the lock with exclusive write access immediately.
+    ///
+    /// Returns `None` if the lock is held, or if tasks are waiting to acquire
+    /// it.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        if self.try_acquire(Kind::Write) {
+            Some(RwLockWriteGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to acquire the lock with upgradable read access immediately.
+    ///
+    /// Returns `None` if a writer or another upgradable reader holds the lock,
+    /// or if tasks are waiting to acquire it.
+    pub fn try_upgradable_read(&self) -> Option<RwLockUpgradableReadGuard<'_, T>> {
+        if self.try_acquire(Kind::UpgradableRead) {
+            Some(RwLockUpgradableReadGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    /// Acquires the lock with shared read access, returning a future which
+    /// resolves to a guard once the lock has been acquired.
+    ///
+    /// Dropping the future before it completes gives up on the lock.
+    pub fn read(&self) -> RwLockReadFuture<'_, T> {
+        RwLockReadFuture {
+            lock: Some(self),
+            wait_key: None,
+        }
+    }
+
+    /// Acquires the lock with exclusive write access, returning a future which
+    /// resolves to a guard once the lock has been acquired.
+    ///
+    /// Dropping the future before it completes gives up on the lock.
+    pub fn write(&self) -> RwLockWriteFuture<'_, T> {
+        RwLockWriteFuture {
+            lock: Some(self),
+            wait_key: None,
+        }
+    }
+
+    /// Acquires the lock with upgradable read access, returning a future which
+    /// resolves to a guard once the lock has been acquired.
+    ///
+    /// Dropping the future before it completes gives up on the lock.
+    pub fn upgradable_read(&self) -> RwLockUpgradableReadFuture<'_, T> {
+        RwLockUpgradableReadFuture {
+            lock: Some(self),
+            wait_key: None,
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `RwLock` mutably, no actual locking needs
+    /// to take place: the mutable borrow statically guarantees no locks exist.
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.value.get() }
+    }
+
+    fn try_acquire(&self, kind: Kind) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.can_acquire_now(kind) {
+            state.acquire(kind);
+            true
+        } else {
+            false
+        }
+    }
+
+    // Polls for the lock on behalf of an acquire future, whose position in
+    // the queue of waiters is tracked by `wait_key`.
+    fn poll_acquire(
+        &self,
+        kind: Kind,
+        wait_key: &mut Option<usize>,
+        cx: &mut task::Context,
+    ) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+
+        let key = match *wait_key {
+            Some(key) => key,
+            None => {
+                if state.can_acquire_now(kind) {
+                    state.acquire(kind);
+                    return Poll::Ready(());
+                }
+
+                let key = state.waiters.insert(Waiter {
+                    kind,
+                    waker: Some(cx.waker().clone()),
+                });
+                if kind == Kind::Upgrade {
+                    state.upgrade = Some(key);
+                } else {
+                    state.queue.push_back(key);
+                }
+                *wait_key = Some(key);
+                return Poll::Pending;
+            }
+        };
+
+        match &mut state.waiters[key].waker {
+            None => {}
+            Some(waker) => {
+                if !waker.will_wake(cx.waker()) {
+                    *waker = cx.waker().clone();
+                }
+                return Poll::Pending;
+            }
+        }
+
+        state.waiters.remove(key);
+        *wait_key = None;
+        Poll::Ready(())
+    }
+
+    // Gives up on the lock on behalf of an acquire future which is dropped
+    // while waiting.
+    fn cancel_acquire(&self, wait_key: usize) {
+        let wakers = {
+            let mut state = match self.state.lock() {
+                Ok(state) => state,
+                Err(_) => return,
+            };
+
+            let waiter = state.waiters.remove(wait_key);
+            match (waiter.waker, waiter.kind) {
+                // The lock was handed over to the future but it was never
+                // polled, release it.
+                (None, kind) => state.release(kind),
+                // A pending upgrade still holds the upgradable read lock
+                (Some(_), Kind::Upgrade) => {
+                    state.upgrade = None;
+                    state.release(Kind::UpgradableRead);
+                }
+                (Some(_), _) => state.queue.retain(|key| *key != wait_key),
+            }
+
+            // Removing a waiter from the front of the queue may let the ones
+            // behind it in.
+            state.grant()
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    fn unlock(&self, kind: Kind) {
+        let wakers = {
+            let mut state = self.state.lock().unwrap();
+            state.release(kind);
+            state.grant()
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl State {
+    fn can_acquire(&self, kind: Kind) -> bool {
+        match kind {
+            Kind::Read => !self.writer,
+            Kind::UpgradableRead => !self.writer && !self.upgradable,
+            Kind::Write => !self.writer && self.readers == 0,
+            // The upgradable reader must be the only reader left
+            Kind::Upgrade => self.readers == 1,
+        }
+    }
+
+    // Whether a task which isn't waiting yet can acquire the lock right away,
+    // without overtaking the waiters.
+    fn can_acquire_now(&self, kind: Kind) -> bool {
+        match kind {
+            Kind::Upgrade => self.can_acquire(kind),
+            _ => self.queue.is_empty() && self.upgrade.is_none() && self.can_acquire(kind),
+        }
+    }
+
+    fn acquire(&mut self, kind: Kind) {
+        match kind {
+            Kind::Read => self.readers += 1,
+            Kind::UpgradableRead => {
+                self.readers += 1;
+                self.upgradable = true;
+            }
+            Kind::Write => self.writer = true,
+            Kind::Upgrade => {
+                self.readers -= 1;
+                self.upgradable = false;
+                self.writer = true;
+            }
+        }
+    }
+
+    // Releases the lock held through a guard of the given kind
+    fn release(&mut self, kind: Kind) {
+        match kind {
+            Kind::Read => self.readers -= 1,
+            Kind::UpgradableRead => {
+                self.readers -= 1;
+                self.upgradable = false;
+            }
+            Kind::Write | Kind::Upgrade => self.writer = false,
+        }
+    }
+
+    // Hands the lock over to as many waiters as possible, in order, returning
+    // the tasks to notify.
+    fn grant(&mut self) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+
+        // A pending upgrade takes priority over the queue, which doesn't move
+        // until the upgrade is done.
+        if let Some(key) = self.upgrade {
+            if self.can_acquire(Kind::Upgrade) {
+                self.upgrade = None;
+                self.acquire(Kind::Upgrade);
+                wakers.extend(self.waiters[key].waker.take());
+            }
+            return wakers;
+        }
+
+        while let Some(&key) = self.queue.front() {
+            let kind = self.waiters[key].kind;
+            if !self.can_acquire(kind) {
+                break;
+            }
+
+            self.queue.pop_front();
+            self.acquire(kind);
+            wakers.extend(self.waiters[key].waker.take());
+        }
+
+        wakers
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("RwLock")
+            .field("readers", &state.readers)
+            .field("writer", &state.writer)
+            .field("waiters", &state.queue.len())
+            .finish()
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> RwLock<T> {
+        RwLock::new(T::default())
+    }
+}
+
+impl<T> From<T> for RwLock<T> {
+    fn from(t: T) -> RwLock<T> {
+        RwLock::new(t)
+    }
+}
+
+/*
+ *
+ * ===== Futures =====
+ *
+ */
+
+/// A future which resolves when the target lock has been acquired with shared
+/// read access.
+///
+/// This value is created by the [`read`](RwLock::read) method.
+#[must_use = "futures do nothing unless polled"]
+pub struct RwLockReadFuture<'a, T: ?Sized + 'a> {
+    // `None` once the guard has been returned
+    lock: Option<&'a RwLock<T>>,
+    wait_key: Option<usize>,
+}
+
+/// A future which resolves when the target lock has been acquired with
+/// exclusive write access.
+///
+/// This value is created by the [`write`](RwLock::write) method.
+#[must_use = "futures do nothing unless polled"]
+pub struct RwLockWriteFuture<'a, T: ?Sized + 'a> {
+    // `None` once the guard has been returned
+    lock: Option<&'a RwLock<T>>,
+    wait_key: Option<usize>,
+}
+
+/// A future which resolves when the target lock has been acquired with
+/// upgradable read access.
+///
+/// This value is created by the [`upgradable_read`](RwLock::upgradable_read)
+/// method.
+#[must_use = "futures do nothing unless polled"]
+pub struct RwLockUpgradableReadFuture<'a, T: ?Sized + 'a> {
+    // `None` once the guard has been returned
+    lock: Option<&'a RwLock<T>>,
+    wait_key: Option<usize>,
+}
+
+/// A future which resolves when an upgradable read lock has been upgraded to
+/// a write lock.
+///
+/// This value is created by the
+/// [`upgrade`](RwLockUpgradableReadGuard::upgrade) method. Dropping it before
+/// it completes releases the lock altogether.
+#[must_use = "futures do nothing unless polled"]
+pub struct RwLockUpgradeFuture<'a, T: ?Sized + 'a> {
+    // `None` once the guard has been returned
+    lock: Option<&'a RwLock<T>>,
+    wait_key: Option<usize>,
+}
+
+macro_rules! acquire_future {
+    ($future:ident, $kind:expr, $guard:ident) => {
+        // Pinning is never projected to fields
+        impl<'a, T: ?Sized> Unpin for $future<'a, T> {}
+
+        impl<'a, T: ?Sized> fmt::Debug for $future<'a, T> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($future))
+                    .field("was_acquired", &self.lock.is_none())
+                    .field("lock", &self.lock)
+                    .field("wait_key", &self.wait_key)
+                    .finish()
+            }
+        }
+
+        impl<'a, T: ?Sized> Future for $future<'a, T> {
+            type Output = $guard<'a, T>;
+
+            fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+                let this = &mut *self;
+                let lock = this.lock.expect(concat!("polled ", stringify!($future), " after completion"));
+
+                if lock.poll_acquire($kind, &mut this.wait_key, cx).is_pending() {
+                    return Poll::Pending;
+                }
+
+                this.lock = None;
+                Poll::Ready($guard { lock })
+            }
+        }
+    }
+}
+
+acquire_future!(RwLockReadFuture, Kind::Read, RwLockReadGuard);
+acquire_future!(RwLockWriteFuture, Kind::Write, RwLockWriteGuard);
+acquire_future!(RwLockUpgradableReadFuture, Kind::UpgradableRead, RwLockUpgradableReadGuard);
+acquire_future!(RwLockUpgradeFuture, Kind::Upgrade, RwLockWriteGuard);
+
+impl<'a, T: ?Sized> Drop for RwLockReadFuture<'a, T> {
+    fn drop(&mut self) {
+        if let (Some(lock), Some(wait_key)) = (self.lock, self.wait_key) {
+            lock.cancel_acquire(wait_key);
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockWriteFuture<'a, T> {
+    fn drop(&mut self) {
+        if let (Some(lock), Some(wait_key)) = (self.lock, self.wait_key) {
+            lock.cancel_acquire(wait_key);
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockUpgradableReadFuture<'a, T> {
+    fn drop(&mut self) {
+        if let (Some(lock), Some(wait_key)) = (self.lock, self.wait_key) {
+            lock.cancel_acquire(wait_key);
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockUpgradeFuture<'a, T> {
+    fn drop(&mut self) {
+        // Until it completes, the future holds the upgradable read lock
+        // given up by the guard it was created from.
+        if let Some(lock) = self.lock {
+            match self.wait_key {
+                Some(wait_key) => lock.cancel_acquire(wait_key),
+                None => lock.unlock(Kind::UpgradableRead),
+            }
+        }
+    }
+}
+
+/*
+ *
+ * ===== Guards =====
+ *
+ */
+
+/// An RAII guard providing shared read access to the data protected by a
+/// [`RwLock`](RwLock).
+///
+/// When this structure is dropped (falls out of scope), the lock will be
+/// released.
+pub struct RwLockReadGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+}
+
+/// An RAII guard providing exclusive write access to the data protected by a
+/// [`RwLock`](RwLock).
+///
+/// When this structure is dropped (falls out of scope), the lock will be
+/// released.
+pub struct RwLockWriteGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+}
+
+/// An RAII guard providing shared read access to the data protected by a
+/// [`RwLock`](RwLock), which can be upgraded to exclusive write access.
+///
+/// When this structure is dropped (falls out of scope), the lock will be
+/// released.
+pub struct RwLockUpgradableReadGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+}
+
+// Sharing the guards shares the data
+unsafe impl<'a, T: ?Sized + Sync> Sync for RwLockReadGuard<'a, T> {}
+unsafe impl<'a, T: ?Sized + Sync> Sync for RwLockWriteGuard<'a, T> {}
+unsafe impl<'a, T: ?Sized + Sync> Sync for RwLockUpgradableReadGuard<'a, T> {}
+
+impl<'a, T: ?Sized> RwLockUpgradableReadGuard<'a, T> {
+    /// Upgrades this guard to exclusive write access, returning a future which
+    /// resolves to a write guard once the other readers are gone.
+    ///
+    /// No writer can acquire the lock in the meantime, and the upgrade takes
+    /// priority over the tasks waiting for the lock.
+    pub fn upgrade(self) -> RwLockUpgradeFuture<'a, T> {
+        let lock = self.lock;
+        // The read lock is handed over to the future
+        mem::forget(self);
+        RwLockUpgradeFuture {
+            lock: Some(lock),
+            wait_key: None,
+        }
+    }
+
+    /// Attempts to upgrade this guard to exclusive write access immediately.
+    ///
+    /// The guard is given back if other readers still hold the lock.
+    pub fn try_upgrade(self) -> Result<RwLockWriteGuard<'a, T>, Self> {
+        if self.lock.try_acquire(Kind::Upgrade) {
+            let lock = self.lock;
+            mem::forget(self);
+            Ok(RwLockWriteGuard { lock })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+macro_rules! guard {
+    ($guard:ident, $kind:expr) => {
+        impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for $guard<'a, T> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($guard))
+                    .field("value", &&**self)
+                    .field("lock", &self.lock)
+                    .finish()
+            }
+        }
+
+        impl<'a, T: ?Sized> Deref for $guard<'a, T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                unsafe { &*self.lock.value.get() }
+            }
+        }
+
+        impl<'a, T: ?Sized> Drop for $guard<'a, T> {
+            fn drop(&mut self) {
+                self.lock.unlock($kind);
+            }
+        }
+    }
+}
+
+guard!(RwLockReadGuard, Kind::Read);
+guard!(RwLockWriteGuard, Kind::Write);
+guard!(RwLockUpgradableReadGuard, Kind::UpgradableRead);
+
+impl<'a, T: ?Sized> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
@@ -0,0 +1,310 @@
+use futures_core::future::Future;
+use futures_core::task::{self, Poll, Waker};
+use slab::Slab;
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::Unpin;
+use std::mem;
+use std::pin::PinMut;
+use std::sync::Mutex as StdMutex;
+use std::vec::Vec;
+
+/// A futures-aware counting semaphore.
+///
+/// A semaphore holds a number of permits, which tasks acquire with
+/// [`acquire`](Semaphore::acquire) and give back by dropping the returned
+/// [`SemaphorePermit`](SemaphorePermit). Sharing a semaphore between tasks,
+/// for example through an `Arc`, bounds how many of them run a section of
+/// code at once.
+///
+/// A task can acquire several permits at once, for operations which weigh
+/// more than others. Tasks get their permits in the order they started
+/// waiting for them: a task waiting for many permits isn't overtaken by tasks
+/// arriving later and asking for fewer.
+pub struct Semaphore {
+    state: StdMutex<State>,
+}
+
+struct State {
+    // Permits which aren't held nor handed over to a waiter
+    permits: usize,
+
+    waiters: Slab<Waiter>,
+
+    // Keys of the waiters in `waiters` which are still waiting, in the order
+    // they started waiting.
+    queue: VecDeque<usize>,
+}
+
+struct Waiter {
+    // Number of permits requested
+    permits: usize,
+
+    // The task to notify once the permits are handed over to this waiter,
+    // `None` once they have been.
+    waker: Option<Waker>,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore holding the given number of permits.
+    pub fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            state: StdMutex::new(State {
+                permits,
+                waiters: Slab::new(),
+                queue: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Returns the number of permits which can currently be acquired.
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().unwrap().permits
+    }
+
+    /// Adds `n` new permits to the semaphore, handing them over to waiting
+    /// tasks if possible.
+    pub fn add_permits(&self, n: usize) {
+        self.release(n);
+    }
+
+    /// Attempts to acquire `n` permits immediately.
+    ///
+    /// Returns `None` if there aren't enough permits available, or if tasks
+    /// are waiting to acquire permits.
+    pub fn try_acquire(&self, n: usize) -> Option<SemaphorePermit<'_>> {
+        let mut state = self.state.lock().unwrap();
+        if state.can_acquire_now(n) {
+            state.permits -= n;
+            Some(SemaphorePermit { semaphore: self, permits: n })
+        } else {
+            None
+        }
+    }
+
+    /// Acquires `n` permits, returning a future which resolves to a guard
+    /// once the permits have been acquired.
+    ///
+    /// Dropping the future before it completes gives up its place in the
+    /// queue of waiting tasks. If permits had already been handed over to it,
+    /// they are passed on to the next tasks waiting for permits.
+    ///
+    /// Since permits are handed over in order, a task asking for more permits
+    /// than the semaphore holds in total waits until enough are added with
+    /// [`add_permits`](Semaphore::add_permits), and so do all the tasks which
+    /// start waiting after it. Without that, they wait forever.
+    pub fn acquire(&self, n: usize) -> SemaphoreAcquireFuture<'_> {
+        SemaphoreAcquireFuture {
+            semaphore: Some(self),
+            permits: n,
+            wait_key: None,
+        }
+    }
+
+    // Polls for permits on behalf of an acquire future, whose position in the
+    // queue of waiters is tracked by `wait_key`.
+    fn poll_acquire(
+        &self,
+        permits: usize,
+        wait_key: &mut Option<usize>,
+        cx: &mut task::Context,
+    ) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+
+        let key = match *wait_key {
+            Some(key) => key,
+            None => {
+                if state.can_acquire_now(permits) {
+                    state.permits -= permits;
+                    return Poll::Ready(());
+                }
+
+                let key = state.waiters.insert(Waiter {
+                    permits,
+                    waker: Some(cx.waker().clone()),
+                });
+                state.queue.push_back(key);
+                *wait_key = Some(key);
+                return Poll::Pending;
+            }
+        };
+
+        match &mut state.waiters[key].waker {
+            None => {}
+            Some(waker) => {
+                if !waker.will_wake(cx.waker()) {
+                    *waker = cx.waker().clone();
+                }
+                return Poll::Pending;
+            }
+        }
+
+        state.waiters.remove(key);
+        *wait_key = None;
+        Poll::Ready(())
+    }
+
+    // Gives up on the permits on behalf of an acquire future which is dropped
+    // while waiting.
+    fn cancel_acquire(&self, wait_key: usize) {
+        let wakers = {
+            let mut state = match self.state.lock() {
+                Ok(state) => state,
+                Err(_) => return,
+            };
+
+            let waiter = state.waiters.remove(wait_key);
+            match waiter.waker {
+                // The permits were handed over to the future but it was never
+                // polled, give them back.
+                None => state.permits += waiter.permits,
+                Some(_) => state.queue.retain(|key| *key != wait_key),
+            }
+
+            // Removing a waiter from the front of the queue may let the ones
+            // behind it in.
+            state.grant()
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    fn release(&self, n: usize) {
+        let wakers = {
+            let mut state = self.state.lock().unwrap();
+            state.permits += n;
+            state.grant()
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl State {
+    // Whether a task which isn't waiting yet can acquire `n` permits right
+    // away, without overtaking the waiters.
+    fn can_acquire_now(&self, n: usize) -> bool {
+        self.queue.is_empty() && self.permits >= n
+    }
+
+    // Hands permits over to as many waiters as possible, in order, returning
+    // the tasks to notify.
+    fn grant(&mut self) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+
+        while let Some(&key) = self.queue.front() {
+            let waiter = &mut self.waiters[key];
+            if waiter.permits > self.permits {
+                break;
+            }
+
+            self.permits -= waiter.permits;
+            wakers.extend(waiter.waker.take());
+            self.queue.pop_front();
+        }
+
+        wakers
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("Semaphore")
+            .field("permits", &state.permits)
+            .field("waiters", &state.queue.len())
+            .finish()
+    }
+}
+
+/// A future which resolves when the requested permits have been acquired from
+/// the target semaphore.
+///
+/// This value is created by the [`acquire`](Semaphore::acquire) method.
+#[must_use = "futures do nothing unless polled"]
+pub struct SemaphoreAcquireFuture<'a> {
+    // `None` once the permit has been returned
+    semaphore: Option<&'a Semaphore>,
+    permits: usize,
+    wait_key: Option<usize>,
+}
+
+// Pinning is never projected to fields
+impl<'a> Unpin for SemaphoreAcquireFuture<'a> {}
+
+impl<'a> fmt::Debug for SemaphoreAcquireFuture<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SemaphoreAcquireFuture")
+            .field("was_acquired", &self.semaphore.is_none())
+            .field("semaphore", &self.semaphore)
+            .field("permits", &self.permits)
+            .field("wait_key", &self.wait_key)
+            .finish()
+    }
+}
+
+impl<'a> Future for SemaphoreAcquireFuture<'a> {
+    type Output = SemaphorePermit<'a>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let semaphore = this.semaphore.expect("polled SemaphoreAcquireFuture after completion");
+
+        if semaphore.poll_acquire(this.permits, &mut this.wait_key, cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        this.semaphore = None;
+        Poll::Ready(SemaphorePermit { semaphore, permits: this.permits })
+    }
+}
+
+impl<'a> Drop for SemaphoreAcquireFuture<'a> {
+    fn drop(&mut self) {
+        if let (Some(semaphore), Some(wait_key)) = (self.semaphore, self.wait_key) {
+            semaphore.cancel_acquire(wait_key);
+        }
+    }
+}
+
+/// An RAII guard holding permits acquired from a
+/// [`Semaphore`](Semaphore).
+///
+/// When this structure is dropped (falls out of scope), the permits are given
+/// back to the semaphore.
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl<'a> SemaphorePermit<'a> {
+    /// Returns the number of permits held by this guard.
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Consumes this guard without giving its permits back to the semaphore,
+    /// which permanently holds that many permits less.
+    pub fn forget(self) {
+        mem::forget(self);
+    }
+}
+
+impl<'a> fmt::Debug for SemaphorePermit<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SemaphorePermit")
+            .field("permits", &self.permits)
+            .field("semaphore", &self.semaphore)
+            .finish()
+    }
+}
+
+impl<'a> Drop for SemaphorePermit<'a> {
+    fn drop(&mut self) {
+        self.semaphore.release(self.permits);
+    }
+}
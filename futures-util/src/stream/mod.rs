@@ -96,6 +96,7 @@ if_std! {
     use std;
     use std::iter::Extend;
     use std::pin::PinBox;
+    use crate::future::CancellationToken;
 
     mod buffer_unordered;
     pub use self::buffer_unordered::BufferUnordered;
@@ -124,6 +125,9 @@ if_std! {
     mod split;
     pub use self::split::{SplitStream, SplitSink, ReuniteError};
 
+    mod take_until_cancelled;
+    pub use self::take_until_cancelled::TakeUntilCancelled;
+
     // ToDo
     // mod select_all;
     // pub use self::select_all::{select_all, SelectAll};
@@ -939,6 +943,20 @@ pub trait StreamExt: Stream {
         Chunks::new(self, capacity)
     }
 
+    /// Ends this stream once `token` is cancelled.
+    ///
+    /// Items are passed through until then. Once the token is cancelled, the
+    /// stream returns `None` without polling the underlying stream again.
+    ///
+    /// This method is only available when the `std` feature of this
+    /// library is activated, and it is activated by default.
+    #[cfg(feature = "std")]
+    fn take_until_cancelled(self, token: CancellationToken) -> TakeUntilCancelled<Self>
+        where Self: Sized
+    {
+        TakeUntilCancelled::new(self, token)
+    }
+
     /// This combinator will attempt to pull items from both streams. Each
     /// stream will be polled in a round-robin fashion, and whenever a stream is
     /// ready to yield an item that item is yielded.
@@ -0,0 +1,89 @@
+use crate::future::CancellationToken;
+use futures_core::stream::Stream;
+use futures_core::task::{self, Poll};
+use pin_utils::{unsafe_pinned, unsafe_unpinned};
+use std::marker::Unpin;
+use std::pin::PinMut;
+
+/// A stream combinator which ends the stream once a `CancellationToken` is
+/// cancelled.
+///
+/// This structure is produced by the `Stream::take_until_cancelled` method.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct TakeUntilCancelled<St> {
+    stream: St,
+    token: CancellationToken,
+    wait_key: Option<usize>,
+}
+
+impl<St: Unpin> Unpin for TakeUntilCancelled<St> {}
+
+impl<St: Stream> TakeUntilCancelled<St> {
+    unsafe_pinned!(stream: St);
+    unsafe_unpinned!(wait_key: Option<usize>);
+
+    pub(super) fn new(stream: St, token: CancellationToken) -> TakeUntilCancelled<St> {
+        TakeUntilCancelled {
+            stream,
+            token,
+            wait_key: None,
+        }
+    }
+
+    /// Acquires a reference to the underlying stream that this combinator is
+    /// pulling from.
+    pub fn get_ref(&self) -> &St {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// combinator is pulling from.
+    ///
+    /// Note that care must be taken to avoid tampering with the state of the
+    /// stream which may otherwise confuse this combinator.
+    pub fn get_mut(&mut self) -> &mut St {
+        &mut self.stream
+    }
+
+    /// Returns the token ending this stream.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl<St> Stream for TakeUntilCancelled<St>
+    where St: Stream,
+{
+    type Item = St::Item;
+
+    fn poll_next(
+        mut self: PinMut<Self>,
+        cx: &mut task::Context
+    ) -> Poll<Option<St::Item>> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(next) = self.stream().poll_next(cx) {
+            return Poll::Ready(next);
+        }
+
+        // Register to be woken up on cancellation
+        let mut wait_key = *self.wait_key();
+        let poll = self.token.poll_cancelled(&mut wait_key, cx);
+        *self.wait_key() = wait_key;
+        match poll {
+            Poll::Ready(()) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<St> Drop for TakeUntilCancelled<St> {
+    fn drop(&mut self) {
+        if let Some(wait_key) = self.wait_key {
+            self.token.unregister(wait_key);
+        }
+    }
+}
@@ -4,10 +4,13 @@ mod spawn;
 pub use self::spawn::{SpawnExt, SpawnError};
 
 if_std! {
-    pub use self::spawn::JoinHandle;
+    pub use self::spawn::{JoinError, JoinHandle};
 
     mod local_waker_ref;
     pub use self::local_waker_ref::{local_waker_ref, local_waker_ref_from_nonlocal, LocalWakerRef};
+
+    mod waker_set;
+    pub use self::waker_set::WakerSet;
 }
 
 #[cfg_attr(
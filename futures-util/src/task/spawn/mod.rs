@@ -8,7 +8,7 @@ if_std! {
 
     mod spawn_with_handle;
     use self::spawn_with_handle::spawn_with_handle;
-    pub use self::spawn_with_handle::JoinHandle;
+    pub use self::spawn_with_handle::{JoinError, JoinHandle};
 }
 
 impl<Sp: ?Sized> SpawnExt for Sp where Sp: Spawn {}
@@ -55,20 +55,25 @@ pub trait SpawnExt: Spawn {
     ///
     /// This method returns a [`Result`] that contains a [`JoinHandle`], or, if
     /// spawning fails, a [`SpawnError`]. [`JoinHandle`] is a future that
-    /// resolves to the output of the spawned future.
+    /// resolves to the output of the spawned future, or to a [`JoinError`]
+    /// if it panicked or was dropped before completing.
     ///
     /// ```
     /// #![feature(async_await, await_macro, futures_api)]
     /// # futures::executor::block_on(async {
     /// use futures::executor::ThreadPool;
     /// use futures::future;
-    /// use futures::task::SpawnExt;
+    /// use futures::task::{JoinError, SpawnExt};
     ///
     /// let mut executor = ThreadPool::new().unwrap();
     ///
     /// let future = future::ready(1);
     /// let join_handle = executor.spawn_with_handle(future).unwrap();
-    /// assert_eq!(await!(join_handle), 1);
+    /// match await!(join_handle) {
+    ///     Ok(output) => assert_eq!(output, 1),
+    ///     Err(JoinError::Panicked(_)) => panic!("the task panicked"),
+    ///     Err(JoinError::Canceled) => panic!("the task was dropped"),
+    /// }
     /// # });
     /// ```
     #[cfg(feature = "std")]
@@ -4,15 +4,22 @@ use futures_channel::oneshot::{self, Sender, Receiver};
 use futures_core::future::Future;
 use futures_core::task::{self, Poll, Spawn, SpawnObjError};
 use pin_utils::{unsafe_pinned, unsafe_unpinned};
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
 use std::marker::Unpin;
 use std::pin::PinMut;
-use std::panic::{self, AssertUnwindSafe};
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 
 /// The join handle returned by
 /// [`spawn_with_handle`](crate::task::SpawnExt::spawn_with_handle).
+///
+/// It resolves to `Ok` with the output of the spawned future, or to `Err`
+/// with a [`JoinError`] if the future panicked or if the executor dropped it
+/// before it completed.
 #[must_use = "futures do nothing unless polled"]
 #[derive(Debug)]
 pub struct JoinHandle<T> {
@@ -31,18 +38,58 @@ impl<T> JoinHandle<T> {
 }
 
 impl<T: Send + 'static> Future for JoinHandle<T> {
-    type Output = T;
+    type Output = Result<T, JoinError>;
 
-    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<T> {
+    fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<Result<T, JoinError>> {
         match self.rx.poll_unpin(cx) {
-            Poll::Ready(Ok(Ok(output))) => Poll::Ready(output),
-            Poll::Ready(Ok(Err(e))) => panic::resume_unwind(e),
-            Poll::Ready(Err(e)) => panic::resume_unwind(Box::new(e)),
+            Poll::Ready(Ok(Ok(output))) => Poll::Ready(Ok(output)),
+            Poll::Ready(Ok(Err(payload))) => Poll::Ready(Err(JoinError::Panicked(payload))),
+            Poll::Ready(Err(_)) => Poll::Ready(Err(JoinError::Canceled)),
             Poll::Pending => Poll::Pending,
         }
     }
 }
 
+/// The error returned by a [`JoinHandle`] when the spawned future did not
+/// complete.
+pub enum JoinError {
+    /// The spawned future panicked, with the given payload.
+    ///
+    /// The panic is caught within the spawned task, so the executor doesn't
+    /// see it: for instance, the panic handler of a `ThreadPool` isn't called
+    /// for it.
+    Panicked(Box<dyn Any + Send>),
+    /// The executor dropped the spawned future before it completed.
+    Canceled,
+}
+
+impl fmt::Debug for JoinError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JoinError::Panicked(_) => fmt.debug_tuple("Panicked").field(&"..").finish(),
+            JoinError::Canceled => fmt.debug_tuple("Canceled").finish(),
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JoinError::Panicked(_) => write!(fmt, "spawned task panicked"),
+            JoinError::Canceled => write!(fmt, "spawned task was dropped before completing"),
+        }
+    }
+}
+
+impl Error for JoinError {
+    fn description(&self) -> &str {
+        match self {
+            JoinError::Panicked(_) => "spawned task panicked",
+            JoinError::Canceled => "spawned task was dropped before completing",
+        }
+    }
+}
+
 struct Wrapped<Fut: Future> {
     tx: Option<Sender<Fut::Output>>,
     keep_running: Arc<AtomicBool>,
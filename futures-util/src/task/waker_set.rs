@@ -0,0 +1,275 @@
+use futures_core::task::{Poll, Waker};
+use slab::Slab;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::SeqCst;
+use std::vec::Vec;
+
+/// A set of tasks waiting for an event, woken up one at a time or all at
+/// once.
+///
+/// Where an [`AtomicWaker`](super::AtomicWaker) holds a single task, a
+/// `WakerSet` holds any number of them, each identified by a key. Waiters
+/// register their task with [`register`](WakerSet::register), and are woken up
+/// in the order they registered by [`wake_one`](WakerSet::wake_one), or all
+/// at once by [`wake_all`](WakerSet::wake_all).
+///
+/// A waiter waiting for a wakeup meant for it, such as a notification handed
+/// out by `wake_one`, can check for it with
+/// [`poll_wakeup`](WakerSet::poll_wakeup) without losing its place in the
+/// queue.
+///
+/// A waiter which gives up on waiting, typically because its future is
+/// dropped, must call [`remove`](WakerSet::remove) to leave the set. If it had
+/// been woken up by `wake_one` in the meantime, it should pass the wakeup on
+/// to the next waiter with another call to `wake_one`.
+///
+/// When the decision to wake up a waiter is made while holding a lock,
+/// [`take_one`](WakerSet::take_one) hands out the wakeup without waking up the
+/// task, which can then be done once the lock is released.
+///
+/// Waking up tasks doesn't lock anything while the set is empty.
+pub struct WakerSet {
+    // Number of waiters registered and not woken up yet
+    len: AtomicUsize,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    // The task of each waiter, `None` once it has been woken up
+    wakers: Slab<Option<Waker>>,
+
+    // Keys of the waiters which haven't been woken up yet, in the order they
+    // registered.
+    queue: VecDeque<usize>,
+}
+
+impl WakerSet {
+    /// Creates a new, empty `WakerSet`.
+    pub fn new() -> WakerSet {
+        WakerSet {
+            len: AtomicUsize::new(0),
+            inner: Mutex::new(Inner {
+                wakers: Slab::new(),
+                queue: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Registers the task to wake up for the waiter identified by `key`.
+    ///
+    /// If `key` is `None`, a new waiter is added to the set and its key
+    /// stored in `key`. Otherwise, the task of that waiter is updated, and the
+    /// waiter goes back to the end of the queue if it had been woken up.
+    ///
+    /// As with `AtomicWaker`, waiters should register **before** checking
+    /// whether the event they wait for happened, and the event should happen
+    /// before the set is woken up, so that no wakeup is lost.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(pin, arbitrary_self_types, futures_api)]
+    /// use futures::future::Future;
+    /// use futures::task::{self, Poll, WakerSet};
+    /// use std::pin::PinMut;
+    /// use std::sync::atomic::AtomicBool;
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// struct Flag {
+    ///     wakers: WakerSet,
+    ///     set: AtomicBool,
+    /// }
+    ///
+    /// struct Wait<'a> {
+    ///     flag: &'a Flag,
+    ///     key: Option<usize>,
+    /// }
+    ///
+    /// impl<'a> Future for Wait<'a> {
+    ///     type Output = ();
+    ///
+    ///     fn poll(mut self: PinMut<Self>, cx: &mut task::Context) -> Poll<()> {
+    ///         let this = &mut *self;
+    ///         this.flag.wakers.register(&mut this.key, cx.waker());
+    ///
+    ///         if this.flag.set.load(SeqCst) {
+    ///             this.flag.wakers.remove(this.key.take().unwrap());
+    ///             Poll::Ready(())
+    ///         } else {
+    ///             Poll::Pending
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// impl<'a> Drop for Wait<'a> {
+    ///     fn drop(&mut self) {
+    ///         if let Some(key) = self.key {
+    ///             self.flag.wakers.remove(key);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn register(&self, key: &mut Option<usize>, waker: &Waker) {
+        let mut inner = self.inner.lock().unwrap();
+
+        let k = match *key {
+            Some(k) => k,
+            None => {
+                let k = inner.wakers.insert(Some(waker.clone()));
+                inner.queue.push_back(k);
+                self.len.fetch_add(1, SeqCst);
+                *key = Some(k);
+                return;
+            }
+        };
+
+        if let Some(old) = &mut inner.wakers[k] {
+            if !old.will_wake(waker) {
+                *old = waker.clone();
+            }
+            return;
+        }
+
+        // The waiter had been woken up, it waits again
+        inner.wakers[k] = Some(waker.clone());
+        inner.queue.push_back(k);
+        self.len.fetch_add(1, SeqCst);
+    }
+
+    /// Checks whether the waiter identified by `key` has been woken up since
+    /// it last registered.
+    ///
+    /// If it has, the waiter is removed from the set, `key` is reset to `None`
+    /// and `Poll::Ready` is returned. Otherwise, this behaves like
+    /// [`register`](WakerSet::register), keeping the waiter's place in the
+    /// queue, and returns `Poll::Pending`.
+    pub fn poll_wakeup(&self, key: &mut Option<usize>, waker: &Waker) -> Poll<()> {
+        let k = match *key {
+            Some(k) => k,
+            None => {
+                self.register(key, waker);
+                return Poll::Pending;
+            }
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        match &mut inner.wakers[k] {
+            Some(old) => {
+                if !old.will_wake(waker) {
+                    *old = waker.clone();
+                }
+                Poll::Pending
+            }
+            None => {
+                inner.wakers.remove(k);
+                *key = None;
+                Poll::Ready(())
+            }
+        }
+    }
+
+    /// Removes the waiter identified by `key` from the set.
+    ///
+    /// Returns `true` if the waiter had been woken up since it last
+    /// registered, in which case a wakeup from `wake_one` meant for it may
+    /// have been lost.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `key` doesn't identify a waiter of the set.
+    pub fn remove(&self, key: usize) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+
+        match inner.wakers.remove(key) {
+            None => true,
+            Some(_) => {
+                inner.queue.retain(|k| *k != key);
+                self.len.fetch_sub(1, SeqCst);
+                false
+            }
+        }
+    }
+
+    /// Wakes up the waiter which has been waiting the longest.
+    ///
+    /// Returns `false` if no waiter was waiting.
+    pub fn wake_one(&self) -> bool {
+        match self.take_one() {
+            Some(waker) => {
+                waker.wake();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Hands a wakeup to the waiter which has been waiting the longest,
+    /// returning its task without waking it up.
+    ///
+    /// The waiter counts as woken up from then on, as with
+    /// [`wake_one`](WakerSet::wake_one), so the caller must wake up the task
+    /// returned. Returns `None` if no waiter was waiting.
+    pub fn take_one(&self) -> Option<Waker> {
+        if self.len.load(SeqCst) == 0 {
+            return None;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        match inner.queue.pop_front() {
+            Some(key) => {
+                self.len.fetch_sub(1, SeqCst);
+                inner.wakers[key].take()
+            }
+            None => None,
+        }
+    }
+
+    /// Wakes up all the waiters, returning how many were waiting.
+    pub fn wake_all(&self) -> usize {
+        if self.len.load(SeqCst) == 0 {
+            return 0;
+        }
+
+        let wakers = {
+            let mut inner = self.inner.lock().unwrap();
+            let Inner { wakers, queue } = &mut *inner;
+            self.len.fetch_sub(queue.len(), SeqCst);
+            queue.drain(..)
+                .filter_map(|key| wakers[key].take())
+                .collect::<Vec<_>>()
+        };
+
+        let n = wakers.len();
+        for waker in wakers {
+            waker.wake();
+        }
+        n
+    }
+
+    /// Returns the number of waiters which haven't been woken up yet.
+    pub fn len(&self) -> usize {
+        self.len.load(SeqCst)
+    }
+
+    /// Returns `true` if no waiter is waiting to be woken up.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for WakerSet {
+    fn default() -> WakerSet {
+        WakerSet::new()
+    }
+}
+
+impl fmt::Debug for WakerSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WakerSet")
+            .field("len", &self.len())
+            .finish()
+    }
+}
@@ -13,8 +13,9 @@
 //! - [Executors](crate::executor) are responsible for running asynchronous
 //!   tasks.
 //!
-//! The crate also contains abstractions for [asynchronous I/O](crate::io) and
-//! [cross-task communication](crate::channel).
+//! The crate also contains abstractions for [asynchronous I/O](crate::io),
+//! [cross-task communication](crate::channel) and
+//! [synchronization](crate::lock).
 //!
 //! Underlying all of this is the *task system*, which is a form of lightweight
 //! threading. Large asynchronous computations are built up using futures,
@@ -65,15 +66,36 @@ pub mod channel {
     //! Cross-task communication.
     //!
     //! Like threads, concurrent tasks sometimes need to communicate with each
-    //! other. This module contains two basic abstractions for doing so:
+    //! other. This module contains a few basic abstractions for doing so:
     //!
     //! - [oneshot](crate::channel::oneshot), a way of sending a single value
     //!   from one task to another.
     //! - [mpsc](crate::channel::mpsc), a multi-producer, single-consumer
     //!   channel for sending values between tasks, analogous to the
     //!   similarly-named structure in the standard library.
-
-    pub use futures_channel::{oneshot, mpsc};
+    //! - [rendezvous](crate::channel::rendezvous), a multi-producer,
+    //!   single-consumer channel without buffering, where sending completes
+    //!   only once the value has been received.
+    //! - [lossy](crate::channel::lossy), a bounded multi-producer,
+    //!   single-consumer channel which drops values instead of making senders
+    //!   wait when it is full.
+    //! - [priority](crate::channel::priority), a multi-producer,
+    //!   single-consumer channel which delivers the values with the highest
+    //!   priority first.
+    //! - [mpmc](crate::channel::mpmc), a multi-producer, multi-consumer
+    //!   channel where each value sent is received by exactly one of the
+    //!   receivers.
+    //! - [broadcast](crate::channel::broadcast), a multi-producer,
+    //!   multi-consumer channel where every value sent is delivered to all
+    //!   receivers.
+    //! - [watch](crate::channel::watch), a single-producer, multi-consumer
+    //!   channel that only retains the latest value sent.
+    //! - [rpc](crate::channel::rpc), a request/response channel for calling
+    //!   into a server task.
+
+    pub use futures_channel::{
+        oneshot, mpsc, rendezvous, lossy, priority, mpmc, broadcast, watch, rpc,
+    };
 }
 
 #[cfg(feature = "compat")]
@@ -158,8 +180,8 @@ pub mod executor {
     pub use futures_executor::{
         BlockingStream,
         Enter, EnterError,
-        LocalSpawn, LocalPool,
-        ThreadPool, ThreadPoolBuilder,
+        LocalSpawn, LocalPool, RemoteSpawn,
+        ShutdownOnIdle, ThreadPool, ThreadPoolBuilder,
         block_on, block_on_stream, enter,
     };
 }
@@ -199,6 +221,9 @@ pub mod future {
     #[cfg(feature = "std")]
     pub use futures_util::future::{
         abortable, Abortable, AbortHandle, AbortRegistration, Aborted,
+        AbortableSinkError,
+        CancellationToken, WaitForCancellation,
+        SingleFlight, SingleFlightCall,
         // For FutureExt:
         CatchUnwind, Shared
 
@@ -240,6 +265,41 @@ pub mod io {
     };
 }
 
+#[cfg(feature = "std")]
+pub mod lock {
+    //! Futures-powered synchronization primitives.
+    //!
+    //! This module contains primitives such as [`Mutex`](crate::lock::Mutex)
+    //! and [`RwLock`](crate::lock::RwLock) which, unlike their counterparts in
+    //! `std::sync`, notify the current task once they become available instead
+    //! of blocking the thread. Their guards can be held across suspension
+    //! points.
+    //!
+    //! A [`Semaphore`](crate::lock::Semaphore) bounds how many tasks run a
+    //! section of code at once, across otherwise independent tasks, while
+    //! [`Notify`](crate::lock::Notify), [`Barrier`](crate::lock::Barrier) and
+    //! [`Condvar`](crate::lock::Condvar) let tasks wait for each other.
+    //! [`OnceCell`](crate::lock::OnceCell) lazily initializes a value with an
+    //! asynchronous initializer which runs only once.
+    //! [`MultiLock`](crate::lock::MultiLock) splits a value into handles owned
+    //! by separate tasks, which can later be reunited.
+
+    pub use futures_util::lock::{
+        Mutex, MutexGuard, MutexLockFuture, OwnedMutexGuard,
+        OwnedMutexLockFuture,
+        RwLock, RwLockReadFuture, RwLockReadGuard, RwLockUpgradableReadFuture,
+        RwLockUpgradableReadGuard, RwLockUpgradeFuture, RwLockWriteFuture,
+        RwLockWriteGuard,
+        Semaphore, SemaphoreAcquireFuture, SemaphorePermit,
+        Notify, NotifiedFuture,
+        Barrier, BarrierWaitFuture, BarrierWaitResult,
+        Condvar, CondvarWaitFuture,
+        OnceCell, GetOrInitFuture, GetOrTryInitFuture,
+        MultiLock, MultiLockAcquire, MultiLockAcquireOwned, MultiLockGuard,
+        OwnedMultiLockGuard, ReuniteAllError,
+    };
+}
+
 pub mod prelude {
     //! A "prelude" for crates using the `futures` crate.
     //!
@@ -324,7 +384,7 @@ pub mod stream {
 
         // For StreamExt:
         BufferUnordered, Buffered, CatchUnwind, Chunks, Collect, SplitStream,
-        SplitSink, ReuniteError,
+        SplitSink, ReuniteError, TakeUntilCancelled,
 
         // ToDo: select_all, SelectAll,
     };
@@ -376,7 +436,8 @@ pub mod task {
 
     #[cfg(feature = "std")]
     pub use futures_util::task::{
-        LocalWakerRef, local_waker_ref, local_waker_ref_from_nonlocal, JoinHandle
+        LocalWakerRef, local_waker_ref, local_waker_ref_from_nonlocal, JoinError, JoinHandle,
+        WakerSet,
     };
 
     #[cfg_attr(
@@ -1,8 +1,10 @@
 #![feature(pin, arbitrary_self_types, futures_api)]
 
-use futures::channel::oneshot;
-use futures::executor::block_on;
-use futures::future::{abortable, Aborted, FutureExt};
+use futures::channel::{mpsc, oneshot};
+use futures::executor::{block_on, block_on_stream};
+use futures::future::{abortable, Abortable, AbortableSinkError, AbortHandle, Aborted, FutureExt};
+use futures::sink::SinkExt;
+use futures::stream::{self, StreamExt};
 use futures::task::Poll;
 use futures_test::task::{panic_context, WakeCounter};
 
@@ -40,3 +42,49 @@ fn abortable_resolves() {
 
     assert_eq!(Ok(Ok(())), block_on(abortable_rx));
 }
+
+#[test]
+fn abortable_stream_terminates() {
+    let (tx, rx) = mpsc::unbounded::<i32>();
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    let mut stream = Abortable::new(rx, abort_registration);
+
+    let wake_counter = WakeCounter::new();
+    let mut cx = panic_context();
+    let cx = &mut cx.with_waker(wake_counter.local_waker());
+    tx.unbounded_send(1).unwrap();
+    assert_eq!(Poll::Ready(Some(1)), stream.poll_next_unpin(cx));
+    assert_eq!(Poll::Pending, stream.poll_next_unpin(cx));
+
+    abort_handle.abort();
+    assert_eq!(1, wake_counter.count());
+    tx.unbounded_send(2).unwrap();
+    assert_eq!(Poll::Ready(None), stream.poll_next_unpin(cx));
+    assert!(stream.is_aborted());
+}
+
+#[test]
+fn abortable_stream_resolves() {
+    let (_abort_handle, abort_registration) = AbortHandle::new_pair();
+    let stream = Abortable::new(stream::iter(vec![1, 2]), abort_registration);
+
+    assert_eq!(block_on_stream(stream).collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[test]
+fn abortable_sink_fails() {
+    let (tx, rx) = mpsc::channel::<i32>(0);
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    let mut sink = Abortable::new(tx, abort_registration);
+
+    block_on(sink.send(1)).unwrap();
+
+    abort_handle.abort();
+    match block_on(sink.send(2)) {
+        Err(AbortableSinkError::Aborted) => {}
+        _ => panic!("sink should be aborted"),
+    }
+    drop(sink);
+
+    assert_eq!(block_on_stream(rx).collect::<Vec<_>>(), vec![1]);
+}
@@ -0,0 +1,93 @@
+#![feature(async_await, await_macro, futures_api, pin, arbitrary_self_types)]
+
+use futures::channel::mpsc;
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{FutureExt, poll_fn};
+use futures::lock::Barrier;
+use futures::stream::StreamExt;
+use futures::task::{Poll, SpawnExt};
+use std::sync::Arc;
+
+#[test]
+fn barrier_releases_group() {
+    block_on(poll_fn(|cx| {
+        let barrier = Barrier::new(3);
+
+        let mut wait1 = barrier.wait();
+        let mut wait2 = barrier.wait();
+        assert!(wait1.poll_unpin(cx).is_pending());
+        assert!(wait2.poll_unpin(cx).is_pending());
+
+        match barrier.wait().poll_unpin(cx) {
+            Poll::Ready(result) => assert!(result.is_leader()),
+            Poll::Pending => panic!("the group should be complete"),
+        }
+        match (wait1.poll_unpin(cx), wait2.poll_unpin(cx)) {
+            (Poll::Ready(r1), Poll::Ready(r2)) => {
+                assert!(!r1.is_leader());
+                assert!(!r2.is_leader());
+            }
+            _ => panic!("the group should be released"),
+        }
+
+        // The barrier can be used again
+        let mut wait1 = barrier.wait();
+        assert!(wait1.poll_unpin(cx).is_pending());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn barrier_dropped_waiter_stops_waiting() {
+    block_on(poll_fn(|cx| {
+        let barrier = Barrier::new(2);
+
+        let mut wait1 = barrier.wait();
+        assert!(wait1.poll_unpin(cx).is_pending());
+        drop(wait1);
+
+        let mut wait2 = barrier.wait();
+        assert!(wait2.poll_unpin(cx).is_pending());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn barrier_one_leader_per_group() {
+    let (tx, rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder()
+        .pool_size(8)
+        .create()
+        .unwrap();
+
+    let barrier = Arc::new(Barrier::new(10));
+    for _ in 0..100 {
+        let tx = tx.clone();
+        let barrier = barrier.clone();
+        pool.clone().spawn(async move {
+            let result = await!(barrier.wait());
+            tx.unbounded_send(result.is_leader()).unwrap();
+        }).unwrap();
+    }
+    drop(tx);
+
+    let results = block_on(rx.collect::<Vec<_>>());
+    assert_eq!(results.len(), 100);
+    assert_eq!(results.iter().filter(|is_leader| **is_leader).count(), 10);
+}
+
+#[test]
+#[should_panic(expected = "polled BarrierWaitFuture after completion")]
+fn barrier_poll_after_release() {
+    block_on(poll_fn(|cx| {
+        let barrier = Barrier::new(1);
+
+        let mut wait = barrier.wait();
+        assert!(wait.poll_unpin(cx).is_ready());
+        let _ = wait.poll_unpin(cx);
+
+        Poll::Ready(())
+    }));
+}
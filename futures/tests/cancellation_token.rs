@@ -0,0 +1,97 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+use futures::channel::{mpsc, oneshot};
+use futures::executor::{block_on, block_on_stream};
+use futures::future::{Abortable, Aborted, CancellationToken, FutureExt};
+use futures::stream::StreamExt;
+use futures::task::Poll;
+use futures_test::task::{panic_context, WakeCounter};
+
+#[test]
+fn cancel_cancels_descendants() {
+    let root = CancellationToken::new();
+    let child = root.child_token();
+    let grandchild = child.child_token();
+    let sibling = root.child_token();
+
+    child.cancel();
+    assert!(child.is_cancelled());
+    assert!(grandchild.is_cancelled());
+    assert!(!root.is_cancelled());
+    assert!(!sibling.is_cancelled());
+
+    root.cancel();
+    assert!(sibling.is_cancelled());
+
+    // Children of a cancelled token start out cancelled
+    assert!(root.child_token().is_cancelled());
+}
+
+#[test]
+fn cancelled_awakens() {
+    let root = CancellationToken::new();
+    let child = root.child_token();
+    let mut cancelled = child.cancelled();
+
+    let wake_counter = WakeCounter::new();
+    let mut cx = panic_context();
+    let cx = &mut cx.with_waker(wake_counter.local_waker());
+    assert_eq!(Poll::Pending, cancelled.poll_unpin(cx));
+    assert_eq!(Poll::Pending, cancelled.poll_unpin(cx));
+
+    root.cancel();
+    assert_eq!(1, wake_counter.count());
+    assert_eq!(Poll::Ready(()), cancelled.poll_unpin(cx));
+    block_on(child.clone().cancelled());
+}
+
+#[test]
+fn abortable_with_token() {
+    let root = CancellationToken::new();
+    let (_tx, rx) = oneshot::channel::<()>();
+    let mut future = Abortable::with_token(rx, &root.child_token());
+
+    let wake_counter = WakeCounter::new();
+    let mut cx = panic_context();
+    let cx = &mut cx.with_waker(wake_counter.local_waker());
+    assert_eq!(Poll::Pending, future.poll_unpin(cx));
+
+    root.cancel();
+    assert_eq!(1, wake_counter.count());
+    assert_eq!(Poll::Ready(Err(Aborted)), future.poll_unpin(cx));
+
+    // Futures tied to a cancelled token are aborted right away
+    let (_tx, rx) = oneshot::channel::<()>();
+    assert_eq!(Err(Aborted), block_on(Abortable::with_token(rx, &root)));
+}
+
+#[test]
+fn take_until_cancelled() {
+    let token = CancellationToken::new();
+    let (tx, rx) = mpsc::unbounded();
+    let mut stream = rx.take_until_cancelled(token.child_token());
+
+    tx.unbounded_send(1).unwrap();
+    let wake_counter = WakeCounter::new();
+    let mut cx = panic_context();
+    let cx = &mut cx.with_waker(wake_counter.local_waker());
+    assert_eq!(Poll::Ready(Some(1)), stream.poll_next_unpin(cx));
+    assert_eq!(Poll::Pending, stream.poll_next_unpin(cx));
+
+    token.cancel();
+    assert_eq!(1, wake_counter.count());
+    tx.unbounded_send(2).unwrap();
+    assert_eq!(Poll::Ready(None), stream.poll_next_unpin(cx));
+}
+
+#[test]
+fn take_until_cancelled_passes_items() {
+    let token = CancellationToken::new();
+    let (tx, rx) = mpsc::unbounded();
+    tx.unbounded_send(1).unwrap();
+    tx.unbounded_send(2).unwrap();
+    drop(tx);
+
+    let stream = block_on_stream(rx.take_until_cancelled(token));
+    assert_eq!(stream.collect::<Vec<_>>(), vec![1, 2]);
+}
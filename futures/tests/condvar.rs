@@ -0,0 +1,90 @@
+#![feature(async_await, await_macro, futures_api, pin, arbitrary_self_types)]
+
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{FutureExt, poll_fn};
+use futures::lock::{Condvar, Mutex};
+use futures::task::{Poll, SpawnExt};
+use std::sync::Arc;
+
+#[test]
+fn condvar_wait_unlocks_mutex() {
+    block_on(poll_fn(|cx| {
+        let mutex = Mutex::new(false);
+        let condvar = Condvar::new();
+
+        let mut wait = condvar.wait(mutex.try_lock().unwrap());
+        assert!(wait.poll_unpin(cx).is_pending());
+
+        *mutex.try_lock().unwrap() = true;
+        condvar.notify_one();
+
+        match wait.poll_unpin(cx) {
+            Poll::Ready(guard) => assert!(*guard),
+            Poll::Pending => panic!("waiter should have been notified"),
+        }
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn condvar_relocks_mutex() {
+    block_on(poll_fn(|cx| {
+        let mutex = Mutex::new(());
+        let condvar = Condvar::new();
+
+        let mut wait = condvar.wait(mutex.try_lock().unwrap());
+        assert!(wait.poll_unpin(cx).is_pending());
+
+        // Notified while the mutex is held, the waiter waits for the mutex
+        let guard = mutex.try_lock().unwrap();
+        condvar.notify_all();
+        assert!(wait.poll_unpin(cx).is_pending());
+
+        drop(guard);
+        assert!(wait.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn condvar_dropped_waiter_passes_notification_on() {
+    block_on(poll_fn(|cx| {
+        let mutex1 = Mutex::new(());
+        let mutex2 = Mutex::new(());
+        let condvar = Condvar::new();
+
+        let mut wait1 = condvar.wait(mutex1.try_lock().unwrap());
+        let mut wait2 = condvar.wait(mutex2.try_lock().unwrap());
+        assert!(wait1.poll_unpin(cx).is_pending());
+        assert!(wait2.poll_unpin(cx).is_pending());
+
+        condvar.notify_one();
+        drop(wait1);
+        assert!(wait2.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn condvar_wait_for_condition() {
+    let pool = ThreadPool::new().unwrap();
+    let pair = Arc::new((Mutex::new(false), Condvar::new()));
+
+    let pair2 = pair.clone();
+    pool.clone().spawn(async move {
+        let (mutex, condvar) = &*pair2;
+        *await!(mutex.lock()) = true;
+        condvar.notify_one();
+    }).unwrap();
+
+    block_on(async {
+        let (mutex, condvar) = &*pair;
+        let mut ready = await!(mutex.lock());
+        while !*ready {
+            ready = await!(condvar.wait(ready));
+        }
+    });
+}
@@ -0,0 +1,104 @@
+#![feature(async_await, await_macro, futures_api, pin, arbitrary_self_types)]
+
+use futures::channel::mpsc;
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{FutureExt, poll_fn};
+use futures::lock::MultiLock;
+use futures::stream::StreamExt;
+use futures::task::{Poll, SpawnExt};
+
+#[test]
+fn multilock_handles_share_value() {
+    let handles = MultiLock::new(0, 3);
+    assert_eq!(handles.len(), 3);
+
+    for handle in &handles {
+        *block_on(handle.lock()) += 1;
+    }
+
+    let guard = handles[0].try_lock().unwrap();
+    assert!(handles[1].try_lock().is_none());
+    assert!(handles[2].clone().try_lock().is_none());
+    assert_eq!(*guard, 3);
+}
+
+#[test]
+fn multilock_owned_guard() {
+    block_on(poll_fn(|cx| {
+        let mut handles = MultiLock::new(0, 2);
+
+        let mut guard = match handles[0].lock_owned().poll_unpin(cx) {
+            Poll::Ready(guard) => guard,
+            Poll::Pending => panic!("lock should be free"),
+        };
+        *guard += 1;
+
+        let mut waiter = handles[1].lock();
+        assert!(waiter.poll_unpin(cx).is_pending());
+
+        // The owned guard outlives the handle it was acquired from
+        drop(handles.remove(0));
+        drop(guard);
+        match waiter.poll_unpin(cx) {
+            Poll::Ready(guard) => assert_eq!(*guard, 1),
+            Poll::Pending => panic!("waiter should hold the lock"),
+        }
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn multilock_reunite_all() {
+    let mut handles = MultiLock::new(1, 3);
+    handles.push(handles[0].clone());
+
+    // All the handles must be returned
+    let other = handles.pop().unwrap();
+    let mut handles = MultiLock::reunite_all(handles).unwrap_err().0;
+    handles.push(other);
+
+    // Owned guards keep the lock alive
+    let guard = block_on(handles[0].lock_owned());
+    let handles = MultiLock::reunite_all(handles).unwrap_err().0;
+    drop(guard);
+
+    assert_eq!(MultiLock::reunite_all(handles).unwrap(), 1);
+}
+
+#[test]
+fn multilock_reunite_all_distinct_locks() {
+    let mut handles = MultiLock::new(1, 1);
+    handles.extend(MultiLock::new(2, 1));
+    assert!(MultiLock::reunite_all(handles).is_err());
+}
+
+#[test]
+fn multilock_contested() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder()
+        .pool_size(16)
+        .create()
+        .unwrap();
+
+    let num_tasks = 100;
+    let handles = MultiLock::new(0, num_tasks);
+    for handle in handles {
+        let tx = tx.clone();
+        pool.clone().spawn(async move {
+            let mut guard = await!(handle.lock_owned());
+            *guard += 1;
+            drop(guard);
+            tx.unbounded_send(handle).unwrap();
+        }).unwrap();
+    }
+
+    let handles = block_on(async {
+        let mut handles = Vec::new();
+        for _ in 0..num_tasks {
+            handles.push(await!(rx.next()).unwrap());
+        }
+        handles
+    });
+    assert_eq!(MultiLock::reunite_all(handles).unwrap(), num_tasks);
+}
@@ -0,0 +1,120 @@
+#![feature(async_await, await_macro, futures_api, pin, arbitrary_self_types)]
+
+use futures::channel::mpsc;
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{FutureExt, poll_fn};
+use futures::lock::Mutex;
+use futures::stream::StreamExt;
+use futures::task::{Poll, SpawnExt};
+use std::sync::Arc;
+
+#[test]
+fn mutex_acquire_uncontested() {
+    let mutex = Mutex::new(());
+    for _ in 0..10 {
+        block_on(mutex.lock());
+    }
+}
+
+#[test]
+fn mutex_try_lock() {
+    let mutex = Mutex::new(1);
+
+    let mut guard = mutex.try_lock().unwrap();
+    assert!(mutex.try_lock().is_none());
+    *guard += 1;
+    drop(guard);
+
+    assert_eq!(*mutex.try_lock().unwrap(), 2);
+    assert_eq!(mutex.into_inner(), 2);
+}
+
+#[test]
+fn mutex_wakes_waiters_in_order() {
+    block_on(poll_fn(|cx| {
+        let mutex = Mutex::new(());
+        let guard = mutex.try_lock().unwrap();
+
+        let mut waiter1 = mutex.lock();
+        let mut waiter2 = mutex.lock();
+        assert!(waiter1.poll_unpin(cx).is_pending());
+        assert!(waiter2.poll_unpin(cx).is_pending());
+
+        // The mutex is handed over to the first waiter, nobody can barge in
+        drop(guard);
+        assert!(mutex.try_lock().is_none());
+        assert!(waiter2.poll_unpin(cx).is_pending());
+
+        let guard1 = match waiter1.poll_unpin(cx) {
+            Poll::Ready(guard) => guard,
+            Poll::Pending => panic!("first waiter should hold the mutex"),
+        };
+        drop(guard1);
+        assert!(waiter2.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn mutex_dropped_waiter_hands_over() {
+    block_on(poll_fn(|cx| {
+        let mutex = Mutex::new(());
+        let guard = mutex.try_lock().unwrap();
+
+        let mut waiter1 = mutex.lock();
+        let mut waiter2 = mutex.lock();
+        assert!(waiter1.poll_unpin(cx).is_pending());
+        assert!(waiter2.poll_unpin(cx).is_pending());
+
+        // The mutex is handed over to `waiter1`, which gives up on it before
+        // being polled again
+        drop(guard);
+        drop(waiter1);
+        assert!(waiter2.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn mutex_owned_guard() {
+    let mutex = Arc::new(Mutex::new(0));
+
+    let mut guard = block_on(mutex.clone().lock_owned());
+    *guard += 1;
+    assert!(mutex.try_lock().is_none());
+    drop(guard);
+
+    assert_eq!(*block_on(mutex.lock()), 1);
+}
+
+#[test]
+fn mutex_contested() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder()
+        .pool_size(16)
+        .create()
+        .unwrap();
+
+    let mutex = Arc::new(Mutex::new(0));
+    let num_tasks = 1000;
+    for _ in 0..num_tasks {
+        let tx = tx.clone();
+        let mutex = mutex.clone();
+        pool.clone().spawn(async move {
+            let mut lock = await!(mutex.lock());
+            *lock += 1;
+            tx.unbounded_send(()).unwrap();
+            drop(lock);
+        }).unwrap();
+    }
+
+    block_on(async {
+        for _ in 0..num_tasks {
+            let () = await!(rx.next()).unwrap();
+        }
+        let lock = await!(mutex.lock());
+        assert_eq!(num_tasks, *lock);
+    })
+}
@@ -0,0 +1,96 @@
+#![feature(futures_api, pin, arbitrary_self_types)]
+
+use futures::executor::block_on;
+use futures::future::{FutureExt, poll_fn};
+use futures::lock::Notify;
+use futures::task::Poll;
+
+#[test]
+fn notify_one_stores_permit() {
+    let notify = Notify::new();
+
+    // The notification isn't lost when nobody is waiting yet
+    notify.notify_one();
+    notify.notify_one();
+    block_on(notify.notified());
+
+    // Only one notification is stored
+    block_on(poll_fn(|cx| {
+        assert!(notify.notified().poll_unpin(cx).is_pending());
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn notify_one_wakes_in_order() {
+    block_on(poll_fn(|cx| {
+        let notify = Notify::new();
+
+        let mut waiter1 = notify.notified();
+        let mut waiter2 = notify.notified();
+        assert!(waiter1.poll_unpin(cx).is_pending());
+        assert!(waiter2.poll_unpin(cx).is_pending());
+
+        notify.notify_one();
+        assert!(waiter2.poll_unpin(cx).is_pending());
+        assert!(waiter1.poll_unpin(cx).is_ready());
+
+        notify.notify_one();
+        assert!(waiter2.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn notify_all_wakes_waiting_tasks() {
+    block_on(poll_fn(|cx| {
+        let notify = Notify::new();
+
+        let mut waiter1 = notify.notified();
+        let mut waiter2 = notify.notified();
+        assert!(waiter1.poll_unpin(cx).is_pending());
+        assert!(waiter2.poll_unpin(cx).is_pending());
+
+        notify.notify_all();
+        assert!(waiter1.poll_unpin(cx).is_ready());
+        assert!(waiter2.poll_unpin(cx).is_ready());
+
+        // Nothing is stored for later waiters
+        assert!(notify.notified().poll_unpin(cx).is_pending());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn notify_all_observed_before_first_poll() {
+    block_on(poll_fn(|cx| {
+        let notify = Notify::new();
+
+        // The condition is checked between creating the future and awaiting it
+        let mut waiter = notify.notified();
+        notify.notify_all();
+        assert!(waiter.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn notify_dropped_waiter_passes_notification_on() {
+    block_on(poll_fn(|cx| {
+        let notify = Notify::new();
+
+        let mut waiter1 = notify.notified();
+        let mut waiter2 = notify.notified();
+        assert!(waiter1.poll_unpin(cx).is_pending());
+        assert!(waiter2.poll_unpin(cx).is_pending());
+
+        notify.notify_one();
+        drop(waiter1);
+        assert!(waiter2.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
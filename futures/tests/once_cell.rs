@@ -0,0 +1,91 @@
+#![feature(futures_api, pin, arbitrary_self_types)]
+
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use futures::future::{self, FutureExt, poll_fn};
+use futures::lock::OnceCell;
+use futures::task::Poll;
+use std::cell::Cell;
+
+#[test]
+fn once_cell_get_or_init() {
+    let cell = OnceCell::new();
+    assert_eq!(cell.get(), None);
+
+    assert_eq!(*block_on(cell.get_or_init(|| future::ready(1))), 1);
+    assert_eq!(*block_on(cell.get_or_init(|| future::ready(2))), 1);
+    assert_eq!(cell.get(), Some(&1));
+    assert_eq!(cell.set(3), Err(3));
+    assert_eq!(cell.into_inner(), Some(1));
+}
+
+#[test]
+fn once_cell_set() {
+    let cell = OnceCell::new();
+    assert_eq!(cell.set(1), Ok(()));
+    assert_eq!(cell.set(2), Err(2));
+    assert_eq!(*block_on(cell.get_or_init(|| future::ready(3))), 1);
+}
+
+#[test]
+fn once_cell_concurrent_callers_wait() {
+    block_on(poll_fn(|cx| {
+        let cell = OnceCell::new();
+        let calls = Cell::new(0);
+        let (tx, rx) = oneshot::channel::<u32>();
+
+        let mut init1 = cell.get_or_init(|| {
+            calls.set(calls.get() + 1);
+            rx.map(|r| r.unwrap())
+        });
+        let mut init2 = cell.get_or_init(|| {
+            calls.set(calls.get() + 1);
+            future::ready(2)
+        });
+        assert!(init1.poll_unpin(cx).is_pending());
+        assert!(init2.poll_unpin(cx).is_pending());
+
+        tx.send(1).unwrap();
+        assert_eq!(init1.poll_unpin(cx), Poll::Ready(&1));
+        assert_eq!(init2.poll_unpin(cx), Poll::Ready(&1));
+        assert_eq!(calls.get(), 1);
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn once_cell_failed_init_lets_waiter_retry() {
+    block_on(poll_fn(|cx| {
+        let cell = OnceCell::new();
+        let (tx, rx) = oneshot::channel::<Result<u32, ()>>();
+
+        let mut init1 = cell.get_or_try_init(|| rx.map(|r| r.unwrap()));
+        let mut init2 = cell.get_or_try_init(|| future::ready(Ok::<_, ()>(2)));
+        assert!(init1.poll_unpin(cx).is_pending());
+        assert!(init2.poll_unpin(cx).is_pending());
+
+        tx.send(Err(())).unwrap();
+        assert_eq!(init1.poll_unpin(cx), Poll::Ready(Err(())));
+        assert_eq!(init2.poll_unpin(cx), Poll::Ready(Ok(&2)));
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn once_cell_cancelled_init_lets_waiter_retry() {
+    block_on(poll_fn(|cx| {
+        let cell = OnceCell::new();
+
+        let mut init1 = cell.get_or_init(|| future::empty());
+        let mut init2 = cell.get_or_init(|| future::ready(2));
+        assert!(init1.poll_unpin(cx).is_pending());
+        assert!(init2.poll_unpin(cx).is_pending());
+
+        drop(init1);
+        assert_eq!(init2.poll_unpin(cx), Poll::Ready(&2));
+
+        Poll::Ready(())
+    }));
+}
@@ -0,0 +1,183 @@
+#![feature(async_await, await_macro, futures_api, pin, arbitrary_self_types)]
+
+use futures::channel::mpsc;
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{FutureExt, poll_fn};
+use futures::lock::RwLock;
+use futures::stream::StreamExt;
+use futures::task::{Poll, SpawnExt};
+use std::sync::Arc;
+
+#[test]
+fn rwlock_concurrent_readers() {
+    let lock = RwLock::new(1);
+
+    let read1 = lock.try_read().unwrap();
+    let read2 = block_on(lock.read());
+    assert_eq!(*read1 + *read2, 2);
+    assert!(lock.try_write().is_none());
+
+    drop(read1);
+    drop(read2);
+    assert!(lock.try_write().is_some());
+}
+
+#[test]
+fn rwlock_try_write() {
+    let lock = RwLock::new(1);
+
+    let mut guard = lock.try_write().unwrap();
+    assert!(lock.try_read().is_none());
+    assert!(lock.try_write().is_none());
+    *guard += 1;
+    drop(guard);
+
+    assert_eq!(*lock.try_read().unwrap(), 2);
+    assert_eq!(lock.into_inner(), 2);
+}
+
+#[test]
+fn rwlock_writer_preference() {
+    block_on(poll_fn(|cx| {
+        let lock = RwLock::new(());
+        let read = lock.try_read().unwrap();
+
+        // Readers arriving after a waiting writer wait behind it
+        let mut writer = lock.write();
+        assert!(writer.poll_unpin(cx).is_pending());
+        assert!(lock.try_read().is_none());
+        let mut reader = lock.read();
+        assert!(reader.poll_unpin(cx).is_pending());
+
+        drop(read);
+        let write = match writer.poll_unpin(cx) {
+            Poll::Ready(guard) => guard,
+            Poll::Pending => panic!("writer should hold the lock"),
+        };
+        assert!(reader.poll_unpin(cx).is_pending());
+
+        drop(write);
+        assert!(reader.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn rwlock_dropped_writer_lets_readers_in() {
+    block_on(poll_fn(|cx| {
+        let lock = RwLock::new(());
+        let read = lock.try_read().unwrap();
+
+        let mut writer = lock.write();
+        let mut reader = lock.read();
+        assert!(writer.poll_unpin(cx).is_pending());
+        assert!(reader.poll_unpin(cx).is_pending());
+
+        drop(writer);
+        assert!(reader.poll_unpin(cx).is_ready());
+        drop(read);
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn rwlock_dropped_waiter_hands_over() {
+    block_on(poll_fn(|cx| {
+        let lock = RwLock::new(());
+        let write = lock.try_write().unwrap();
+
+        let mut writer1 = lock.write();
+        let mut writer2 = lock.write();
+        assert!(writer1.poll_unpin(cx).is_pending());
+        assert!(writer2.poll_unpin(cx).is_pending());
+
+        // The lock is handed over to `writer1`, which gives up on it before
+        // being polled again
+        drop(write);
+        drop(writer1);
+        assert!(writer2.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn rwlock_upgradable_read() {
+    block_on(poll_fn(|cx| {
+        let lock = RwLock::new(0);
+
+        let upgradable = lock.try_upgradable_read().unwrap();
+        assert!(lock.try_upgradable_read().is_none());
+        let read = lock.try_read().unwrap();
+        let upgradable = upgradable.try_upgrade().unwrap_err();
+
+        // The upgrade goes before the waiting writer
+        let mut writer = lock.write();
+        assert!(writer.poll_unpin(cx).is_pending());
+        let mut upgrade = upgradable.upgrade();
+        assert!(upgrade.poll_unpin(cx).is_pending());
+
+        drop(read);
+        let mut write = match upgrade.poll_unpin(cx) {
+            Poll::Ready(guard) => guard,
+            Poll::Pending => panic!("upgrade should hold the lock"),
+        };
+        *write += 1;
+        assert!(writer.poll_unpin(cx).is_pending());
+
+        drop(write);
+        assert!(writer.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn rwlock_dropped_upgrade_releases_lock() {
+    let lock = RwLock::new(());
+
+    let upgradable = lock.try_upgradable_read().unwrap();
+    let read = lock.try_read().unwrap();
+    let mut upgrade = upgradable.upgrade();
+    assert!(block_on(poll_fn(|cx| Poll::Ready(upgrade.poll_unpin(cx).is_pending()))));
+
+    drop(upgrade);
+    drop(read);
+    assert!(lock.try_write().is_some());
+}
+
+#[test]
+fn rwlock_contested() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder()
+        .pool_size(16)
+        .create()
+        .unwrap();
+
+    let lock = Arc::new(RwLock::new(0));
+    let num_tasks = 1000;
+    for i in 0..num_tasks {
+        let tx = tx.clone();
+        let lock = lock.clone();
+        pool.clone().spawn(async move {
+            if i % 2 == 0 {
+                let mut write = await!(lock.write());
+                *write += 1;
+            } else {
+                let read = await!(lock.read());
+                assert!(*read <= num_tasks);
+            }
+            tx.unbounded_send(()).unwrap();
+        }).unwrap();
+    }
+
+    block_on(async {
+        for _ in 0..num_tasks {
+            let () = await!(rx.next()).unwrap();
+        }
+        let read = await!(lock.read());
+        assert_eq!(num_tasks / 2, *read);
+    })
+}
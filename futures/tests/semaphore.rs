@@ -0,0 +1,134 @@
+#![feature(async_await, await_macro, futures_api, pin, arbitrary_self_types)]
+
+use futures::channel::mpsc;
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{FutureExt, poll_fn};
+use futures::lock::Semaphore;
+use futures::stream::StreamExt;
+use futures::task::{Poll, SpawnExt};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[test]
+fn semaphore_try_acquire() {
+    let semaphore = Semaphore::new(3);
+
+    let permit = semaphore.try_acquire(2).unwrap();
+    assert_eq!(permit.num_permits(), 2);
+    assert_eq!(semaphore.available_permits(), 1);
+    assert!(semaphore.try_acquire(2).is_none());
+    assert!(semaphore.try_acquire(1).is_some());
+
+    drop(permit);
+    assert_eq!(semaphore.available_permits(), 3);
+}
+
+#[test]
+fn semaphore_forget_and_add_permits() {
+    let semaphore = Semaphore::new(2);
+
+    semaphore.try_acquire(1).unwrap().forget();
+    assert_eq!(semaphore.available_permits(), 1);
+
+    semaphore.add_permits(3);
+    assert_eq!(semaphore.available_permits(), 4);
+}
+
+#[test]
+fn semaphore_waiters_in_order() {
+    block_on(poll_fn(|cx| {
+        let semaphore = Semaphore::new(2);
+        let permit = semaphore.try_acquire(2).unwrap();
+
+        // A small request arriving later doesn't overtake a large one
+        let mut large = semaphore.acquire(2);
+        let mut small = semaphore.acquire(1);
+        assert!(large.poll_unpin(cx).is_pending());
+        assert!(small.poll_unpin(cx).is_pending());
+        assert!(semaphore.try_acquire(1).is_none());
+
+        drop(permit);
+        let permit = match large.poll_unpin(cx) {
+            Poll::Ready(permit) => permit,
+            Poll::Pending => panic!("first waiter should hold the permits"),
+        };
+        assert!(small.poll_unpin(cx).is_pending());
+
+        drop(permit);
+        assert!(small.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn semaphore_dropped_waiter_gives_up_its_place() {
+    block_on(poll_fn(|cx| {
+        let semaphore = Semaphore::new(2);
+        let permit = semaphore.try_acquire(1).unwrap();
+
+        let mut large = semaphore.acquire(2);
+        let mut small = semaphore.acquire(1);
+        assert!(large.poll_unpin(cx).is_pending());
+        assert!(small.poll_unpin(cx).is_pending());
+
+        drop(large);
+        assert!(small.poll_unpin(cx).is_ready());
+        drop(permit);
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn semaphore_dropped_waiter_hands_over() {
+    block_on(poll_fn(|cx| {
+        let semaphore = Semaphore::new(1);
+        let permit = semaphore.try_acquire(1).unwrap();
+
+        let mut waiter1 = semaphore.acquire(1);
+        let mut waiter2 = semaphore.acquire(1);
+        assert!(waiter1.poll_unpin(cx).is_pending());
+        assert!(waiter2.poll_unpin(cx).is_pending());
+
+        // The permit is handed over to `waiter1`, which gives up on it before
+        // being polled again
+        drop(permit);
+        drop(waiter1);
+        assert!(waiter2.poll_unpin(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn semaphore_bounds_concurrency() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder()
+        .pool_size(16)
+        .create()
+        .unwrap();
+
+    let semaphore = Arc::new(Semaphore::new(4));
+    let running = Arc::new(AtomicUsize::new(0));
+    let num_tasks = 1000;
+    for _ in 0..num_tasks {
+        let tx = tx.clone();
+        let semaphore = semaphore.clone();
+        let running = running.clone();
+        pool.clone().spawn(async move {
+            let permit = await!(semaphore.acquire(1));
+            assert!(running.fetch_add(1, Ordering::SeqCst) < 4);
+            running.fetch_sub(1, Ordering::SeqCst);
+            drop(permit);
+            tx.unbounded_send(()).unwrap();
+        }).unwrap();
+    }
+
+    block_on(async {
+        for _ in 0..num_tasks {
+            let () = await!(rx.next()).unwrap();
+        }
+    });
+    assert_eq!(semaphore.available_permits(), 4);
+}
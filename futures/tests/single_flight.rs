@@ -0,0 +1,109 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use futures::future::{self, FutureExt, SingleFlight, poll_fn};
+use futures::task::Poll;
+use std::cell::Cell;
+
+#[test]
+fn single_flight_deduplicates_calls() {
+    block_on(poll_fn(|cx| {
+        let group = SingleFlight::new();
+        let calls = Cell::new(0);
+        let (tx, rx) = oneshot::channel::<i32>();
+        let mut rx = Some(rx);
+        let mut make = || {
+            calls.set(calls.get() + 1);
+            rx.take().unwrap()
+        };
+
+        let mut call1 = group.call("a", &mut make);
+        let mut call2 = group.call("a", &mut make);
+        assert!(call1.poll_unpin(cx).is_pending());
+        assert!(call2.poll_unpin(cx).is_pending());
+        assert_eq!(calls.get(), 1);
+        assert_eq!(group.len(), 1);
+
+        tx.send(1).unwrap();
+        assert_eq!(call2.poll_unpin(cx), Poll::Ready(Ok(1)));
+        assert_eq!(call1.poll_unpin(cx), Poll::Ready(Ok(1)));
+
+        // The key is evicted once the future completed
+        assert!(group.is_empty());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn single_flight_distinct_keys() {
+    let group = SingleFlight::new();
+    let calls = Cell::new(0);
+    let make = |x| {
+        calls.set(calls.get() + 1);
+        future::ready(x)
+    };
+
+    assert_eq!(block_on(group.call(1, || make(1))), 1);
+    assert_eq!(block_on(group.call(2, || make(2))), 2);
+    assert_eq!(block_on(group.call(1, || make(3))), 3);
+    assert_eq!(calls.get(), 3);
+}
+
+#[test]
+fn single_flight_cache_ok() {
+    let group = SingleFlight::cache_ok();
+
+    assert_eq!(block_on(group.call("a", || future::ready(Err(1)))), Err(1));
+    assert!(group.is_empty());
+
+    assert_eq!(block_on(group.call("a", || future::ready(Ok(2)))), Ok(2));
+    assert_eq!(block_on(group.call("a", || future::ready(Ok(3)))), Ok(2));
+    assert_eq!(group.len(), 1);
+
+    assert!(group.forget(&"a"));
+    assert_eq!(block_on(group.call("a", || future::ready(Ok(4)))), Ok(4));
+}
+
+#[test]
+fn single_flight_dropped_callers_evict() {
+    block_on(poll_fn(|cx| {
+        let group = SingleFlight::new();
+        let (tx, rx) = oneshot::channel::<i32>();
+
+        let mut call = group.call("a", || rx);
+        assert!(call.poll_unpin(cx).is_pending());
+        drop(call);
+
+        // The future was dropped along with its last caller
+        assert!(group.is_empty());
+        assert!(tx.is_canceled());
+
+        let (tx, rx) = oneshot::channel::<i32>();
+        let mut call = group.call("a", || rx);
+        tx.send(2).unwrap();
+        assert_eq!(call.poll_unpin(cx), Poll::Ready(Ok(2)));
+
+        Poll::Ready(())
+    }));
+}
+
+
+#[test]
+fn single_flight_reentrant_call() {
+    let group = SingleFlight::new();
+
+    let outer = group.call("a", || {
+        // Calling into the group while creating the future doesn't deadlock
+        assert!(group.is_empty());
+        let inner = group.call("a", || future::ready(1));
+        assert_eq!(group.len(), 1);
+        drop(inner);
+        future::ready(2)
+    });
+
+    // The flight created by the inner call was dropped with it
+    assert_eq!(block_on(outer), 2);
+    assert!(group.is_empty());
+}
@@ -0,0 +1,125 @@
+#![feature(futures_api)]
+
+use futures::task::WakerSet;
+use futures_test::task::{panic_context, WakeCounter};
+
+#[test]
+fn waker_set_wake_one_in_order() {
+    let set = WakerSet::new();
+    let counter1 = WakeCounter::new();
+    let counter2 = WakeCounter::new();
+    let mut cx = panic_context();
+
+    let (mut key1, mut key2) = (None, None);
+    set.register(&mut key1, cx.with_waker(counter1.local_waker()).waker());
+    set.register(&mut key2, cx.with_waker(counter2.local_waker()).waker());
+    assert_eq!(set.len(), 2);
+
+    assert!(set.wake_one());
+    assert_eq!((counter1.count(), counter2.count()), (1, 0));
+    assert!(set.wake_one());
+    assert_eq!((counter1.count(), counter2.count()), (1, 1));
+    assert!(!set.wake_one());
+    assert!(set.is_empty());
+}
+
+#[test]
+fn waker_set_wake_all() {
+    let set = WakerSet::new();
+    let counter = WakeCounter::new();
+    let mut cx = panic_context();
+    let cx = &mut cx.with_waker(counter.local_waker());
+
+    let (mut key1, mut key2) = (None, None);
+    set.register(&mut key1, cx.waker());
+    set.register(&mut key2, cx.waker());
+    assert_eq!(set.wake_all(), 2);
+    assert_eq!(counter.count(), 2);
+
+    // A woken up waiter registering again waits again
+    set.register(&mut key1, cx.waker());
+    assert_eq!(set.len(), 1);
+    assert_eq!(set.wake_all(), 1);
+    assert_eq!(counter.count(), 3);
+}
+
+#[test]
+fn waker_set_register_twice() {
+    let set = WakerSet::new();
+    let counter = WakeCounter::new();
+    let mut cx = panic_context();
+    let cx = &mut cx.with_waker(counter.local_waker());
+
+    let mut key = None;
+    set.register(&mut key, cx.waker());
+    set.register(&mut key, cx.waker());
+    assert_eq!(set.len(), 1);
+    assert!(set.wake_one());
+    assert_eq!(counter.count(), 1);
+}
+
+#[test]
+fn waker_set_remove() {
+    let set = WakerSet::new();
+    let counter = WakeCounter::new();
+    let mut cx = panic_context();
+    let cx = &mut cx.with_waker(counter.local_waker());
+
+    let (mut key1, mut key2) = (None, None);
+    set.register(&mut key1, cx.waker());
+    set.register(&mut key2, cx.waker());
+
+    // A waiter leaving before being woken up isn't woken up
+    assert!(!set.remove(key1.unwrap()));
+    assert_eq!(set.len(), 1);
+
+    // A waiter leaving after being woken up reports it
+    assert!(set.wake_one());
+    assert!(set.remove(key2.unwrap()));
+    assert!(set.is_empty());
+    assert_eq!(counter.count(), 1);
+}
+
+#[test]
+fn waker_set_poll_wakeup() {
+    let set = WakerSet::new();
+    let counter = WakeCounter::new();
+    let mut cx = panic_context();
+    let cx = &mut cx.with_waker(counter.local_waker());
+
+    let (mut key1, mut key2) = (None, None);
+    assert!(set.poll_wakeup(&mut key1, cx.waker()).is_pending());
+    assert!(set.poll_wakeup(&mut key2, cx.waker()).is_pending());
+
+    // Polling again keeps the place in the queue
+    assert!(set.poll_wakeup(&mut key1, cx.waker()).is_pending());
+    assert!(set.wake_one());
+    assert!(set.poll_wakeup(&mut key2, cx.waker()).is_pending());
+    assert!(set.poll_wakeup(&mut key1, cx.waker()).is_ready());
+    assert_eq!(key1, None);
+    assert_eq!(set.len(), 1);
+
+    assert!(!set.remove(key2.unwrap()));
+    assert!(set.is_empty());
+}
+
+#[test]
+fn waker_set_take_one() {
+    let set = WakerSet::new();
+    let counter = WakeCounter::new();
+    let mut cx = panic_context();
+    let cx = &mut cx.with_waker(counter.local_waker());
+
+    let mut key = None;
+    set.register(&mut key, cx.waker());
+
+    // The waiter is woken up, but its task is left to the caller
+    let waker = set.take_one().unwrap();
+    assert!(set.is_empty());
+    assert_eq!(counter.count(), 0);
+    waker.wake();
+    assert_eq!(counter.count(), 1);
+    assert!(set.poll_wakeup(&mut key, cx.waker()).is_ready());
+
+    assert!(set.take_one().is_none());
+}